@@ -0,0 +1,239 @@
+use std::{collections::HashMap, sync::{atomic::{AtomicU64, Ordering}, mpsc::{SendError, Sender}, Arc}};
+
+use revolt_database::events::client::EventV1;
+use reqwest::StatusCode;
+use revolt_models::v0::{AllMemberResponse, BulkMessageResponse, ChannelUnread, DataMessageSend, Member, User, UserProfile};
+use tokio::{sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender}, task::JoinHandle};
+
+use crate::{http::{EditMember, EditRole, HttpClient}, notifications::Notification, state::ConnectionState};
+
+pub type ActionId = u64;
+
+/// A side effect requested by the UI, performed by the [`ActionService`] in the background.
+#[derive(Debug)]
+pub enum Action {
+    SendMessage { channel: String, body: DataMessageSend },
+    EditMessage { channel: String, message: String, content: String },
+    DeleteMessage { channel: String, message: String },
+    DeleteMessages { channel: String, ids: Vec<String> },
+    React { channel: String, message: String, emoji: String },
+    Unreact { channel: String, message: String, emoji: String },
+    Ack { channel: String, message: String },
+    FetchUnreads,
+    FetchHistory { channel: String, before: Option<String> },
+    FetchUser(String),
+    FetchProfile(String),
+    FetchMember { server: String, user: String },
+    FetchMembers(String),
+    EditMember { server: String, user: String, body: EditMember },
+    Kick { server: String, user: String },
+    Ban { server: String, user: String, reason: Option<String> },
+    CreateRole { server: String, name: String },
+    EditRole { server: String, role: String, body: EditRole },
+    SetRolePermissions { server: String, role: String, allow: u64, deny: u64 },
+    DeleteRole { server: String, role: String },
+}
+
+impl Action {
+    /// What the action does, used in error messages.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Action::SendMessage { .. } => "send message",
+            Action::EditMessage { .. } => "edit message",
+            Action::DeleteMessage { .. } | Action::DeleteMessages { .. } => "delete messages",
+            Action::React { .. } => "add reaction",
+            Action::Unreact { .. } => "remove reaction",
+            Action::Ack { .. } => "mark channel as read",
+            Action::FetchUnreads => "load unread messages",
+            Action::FetchHistory { .. } => "load messages",
+            Action::FetchUser(_) => "load user",
+            Action::FetchProfile(_) => "load profile",
+            Action::FetchMember { .. } => "load member",
+            Action::FetchMembers(_) => "load member list",
+            Action::EditMember { .. } => "edit member",
+            Action::Kick { .. } => "kick member",
+            Action::Ban { .. } => "ban member",
+            Action::CreateRole { .. } => "create role",
+            Action::EditRole { .. } => "edit role",
+            Action::SetRolePermissions { .. } => "set role permissions",
+            Action::DeleteRole { .. } => "delete role",
+        }
+    }
+
+    /// Whether the action happens without the user asking for it, failures of these are only logged.
+    pub fn is_background(&self) -> bool {
+        matches!(self,
+            Action::Ack { .. } | Action::FetchUnreads | Action::FetchHistory { before: None, .. } | Action::FetchUser(_)
+                | Action::FetchProfile(_) | Action::FetchMember { .. })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ActionError {
+    pub action: &'static str,
+    pub message: String,
+    /// Status of the response when the server answered with an error.
+    pub status: Option<StatusCode>,
+    pub cancelled: bool,
+    pub background: bool,
+}
+
+/// Data returned by an action which the UI needs to apply, everything else is picked up from the gateway.
+pub enum Output {
+    None,
+    History(String, BulkMessageResponse),
+    User(User),
+    Profile(String, UserProfile),
+    Member(Member),
+    Members(String, AllMemberResponse),
+    Unreads(Vec<ChannelUnread>),
+}
+
+/// Everything sent from the background to the UI thread.
+pub enum AppEvent {
+    Gateway(EventV1),
+    ActionFinished { id: ActionId, result: Result<Output, ActionError> },
+    Notify(Notification),
+    Connection(ConnectionState),
+    /// The gateway rejected the account's token or it was logged out elsewhere, with a reason to show.
+    SignedOut(String),
+}
+
+pub type AccountId = u64;
+
+/// Sends events to the UI thread tagged with the account they belong to, every signed in account has its own.
+#[derive(Clone)]
+pub struct EventSender {
+    account: AccountId,
+    sender: Sender<(AccountId, AppEvent)>,
+}
+
+impl EventSender {
+    pub fn new(account: AccountId, sender: Sender<(AccountId, AppEvent)>) -> Self {
+        Self { account, sender }
+    }
+
+    pub fn send(&self, event: AppEvent) -> Result<(), SendError<AppEvent>> {
+        self.sender.send((self.account, event)).map_err(|SendError((_, event))| SendError(event))
+    }
+}
+
+enum Command {
+    Run(ActionId, Action),
+    Cancel(ActionId),
+}
+
+/// Handle used by components to dispatch actions, cheap to clone.
+#[derive(Clone)]
+pub struct Actions {
+    sender: UnboundedSender<Command>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Actions {
+    pub fn new() -> (Self, ActionService) {
+        let (sender, receiver) = unbounded_channel();
+
+        (Self { sender, next_id: Arc::new(AtomicU64::new(0)) }, ActionService { receiver })
+    }
+
+    /// Queues the action, its result arrives later as [`AppEvent::ActionFinished`] with the returned id.
+    pub fn dispatch(&self, action: Action) -> ActionId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        self.sender.send(Command::Run(id, action)).ok();
+
+        id
+    }
+
+    /// Aborts the action if it's still running, it finishes with a cancelled error.
+    pub fn cancel(&self, id: ActionId) {
+        self.sender.send(Command::Cancel(id)).ok();
+    }
+}
+
+pub struct ActionService {
+    receiver: UnboundedReceiver<Command>,
+}
+
+impl ActionService {
+    pub async fn run(mut self, http: HttpClient, events: EventSender) {
+        let mut tasks = HashMap::<ActionId, (&'static str, bool, JoinHandle<()>)>::new();
+
+        while let Some(command) = self.receiver.recv().await {
+            tasks.retain(|_, (_, _, task)| !task.is_finished());
+
+            match command {
+                Command::Run(id, action) => {
+                    let http = http.clone();
+                    let events = events.clone();
+                    let description = action.describe();
+                    let background = action.is_background();
+
+                    // Only what the action is, payloads can hold message contents and ban reasons.
+                    if cfg!(debug_assertions) {
+                        println!("Action {id}: {description}");
+                    };
+
+                    let task = tokio::spawn(async move {
+                        let result = perform(&http, action).await.map_err(|e| ActionError {
+                            action: description,
+                            message: e.to_string(),
+                            status: e.status(),
+                            cancelled: false,
+                            background,
+                        });
+
+                        events.send(AppEvent::ActionFinished { id, result }).ok();
+                    });
+
+                    tasks.insert(id, (description, background, task));
+                },
+                Command::Cancel(id) => {
+                    // A task which already finished has sent its result, cancelling it would finish it twice.
+                    if let Some((description, background, task)) = tasks.remove(&id).filter(|(_, _, task)| !task.is_finished()) {
+                        if cfg!(debug_assertions) {
+                            println!("Action {id} cancelled");
+                        };
+
+                        task.abort();
+
+                        let error = ActionError { action: description, message: "Cancelled".to_string(), status: None, cancelled: true, background };
+                        events.send(AppEvent::ActionFinished { id, result: Err(error) }).ok();
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn perform(http: &HttpClient, action: Action) -> Result<Output, reqwest::Error> {
+    match action {
+        Action::SendMessage { channel, body } => http.send_message(&channel, &body).await.map(|_| Output::None),
+        Action::EditMessage { channel, message, content } => http.edit_message(&channel, &message, &content).await.map(|_| Output::None),
+        Action::DeleteMessage { channel, message } => http.delete_message(&channel, &message).await.map(|_| Output::None),
+        Action::DeleteMessages { channel, ids } => {
+            for chunk in ids.chunks(100) {
+                http.delete_messages(&channel, chunk).await?;
+            }
+
+            Ok(Output::None)
+        },
+        Action::React { channel, message, emoji } => http.react(&channel, &message, &emoji).await.map(|_| Output::None),
+        Action::Unreact { channel, message, emoji } => http.unreact(&channel, &message, &emoji).await.map(|_| Output::None),
+        Action::Ack { channel, message } => http.ack_message(&channel, &message).await.map(|_| Output::None),
+        Action::FetchUnreads => http.fetch_unreads().await.map(Output::Unreads),
+        Action::FetchHistory { channel, before } => http.fetch_messages(&channel, before.as_deref()).await.map(|history| Output::History(channel, history)),
+        Action::FetchUser(user) => http.fetch_user(&user).await.map(Output::User),
+        Action::FetchProfile(user) => http.fetch_profile(&user).await.map(|profile| Output::Profile(user, profile)),
+        Action::FetchMember { server, user } => http.fetch_member(&server, &user).await.map(Output::Member),
+        Action::FetchMembers(server) => http.fetch_members(&server).await.map(|members| Output::Members(server, members)),
+        Action::EditMember { server, user, body } => http.edit_member(&server, &user, &body).await.map(|_| Output::None),
+        Action::Kick { server, user } => http.kick_member(&server, &user).await.map(|_| Output::None),
+        Action::Ban { server, user, reason } => http.ban_member(&server, &user, reason.as_deref()).await.map(|_| Output::None),
+        Action::CreateRole { server, name } => http.create_role(&server, &name).await.map(|_| Output::None),
+        Action::EditRole { server, role, body } => http.edit_role(&server, &role, &body).await.map(|_| Output::None),
+        Action::SetRolePermissions { server, role, allow, deny } => http.set_role_permissions(&server, &role, allow, deny).await.map(|_| Output::None),
+        Action::DeleteRole { server, role } => http.delete_role(&server, &role).await.map(|_| Output::None),
+    }
+}
@@ -0,0 +1,542 @@
+use std::{collections::HashMap, sync::mpsc::{Receiver, Sender}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{actions::{AccountId, Action, Actions, AppEvent, EventSender}, auth::{Credentials, LoginResponse, MfaMethod, Session}, http::{HttpClient, TokenKind}, instance::{self, Instance, InstanceSettings, DEFAULT_INSTANCE}, notifications::{Notification, Notifier}, state::{update_state, GlobalState, Request, TaskHandle, UnreadSummary}, storage, websocket};
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+const LEGACY_SESSION_FILE: &str = "session.json";
+
+pub struct MfaChallenge {
+    pub ticket: String,
+    pub methods: Vec<MfaMethod>,
+    pub method: MfaMethod,
+    pub code: String,
+}
+
+/// The forms reachable from the login screen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuthPage {
+    Login,
+    Register,
+    Verify,
+    ResendVerification,
+    RequestReset,
+    ConfirmReset,
+    BotToken,
+}
+
+pub struct LoginScreen {
+    pub instance_url: String,
+    /// The instance the forms below talk to, once its configuration has been fetched.
+    pub instance: Option<Instance>,
+    pub connecting: Option<Request<Result<Instance, String>>>,
+    pub instance_error: Option<String>,
+    pub recent_instances: Vec<String>,
+
+    pub page: AuthPage,
+    pub email: String,
+    pub password: String,
+    pub session_name: String,
+    pub captcha: String,
+    pub remember: bool,
+    pub mfa: Option<MfaChallenge>,
+    pub request: Option<Request<Result<LoginResponse, String>>>,
+    pub error: Option<String>,
+
+    pub invite: String,
+    /// Verification code or password reset token, pasted from an email.
+    pub code: String,
+    pub new_password: String,
+    pub log_out_other_sessions: bool,
+    /// Request from one of the account forms, with the page to show and a message once it succeeds.
+    pub pending: Option<Request<Result<(AuthPage, &'static str), String>>>,
+    pub notice: Option<&'static str>,
+
+    pub bot_token: String,
+    pub bot_check: Option<Request<Result<(), String>>>,
+}
+
+impl LoginScreen {
+    /// Starts at `instance` if it's already known, otherwise starts fetching the configuration for `instance_url`.
+    pub fn new(instance_url: String, instance: Option<Instance>) -> Self {
+        let mut screen = Self {
+            instance_url,
+            instance: None,
+            connecting: None,
+            instance_error: None,
+            // Problems reading the settings were already reported at startup.
+            recent_instances: InstanceSettings::load().unwrap_or_default().recent_instances,
+
+            page: AuthPage::Login,
+            email: String::new(),
+            password: String::new(),
+            session_name: "ImRevolt".to_string(),
+            captcha: String::new(),
+            remember: true,
+            mfa: None,
+            request: None,
+            error: None,
+
+            invite: String::new(),
+            code: String::new(),
+            new_password: String::new(),
+            log_out_other_sessions: true,
+            pending: None,
+            notice: None,
+
+            bot_token: String::new(),
+            bot_check: None,
+        };
+
+        match instance.filter(|instance| instance.base_url == screen.instance_url) {
+            Some(instance) => screen.instance = Some(instance),
+            None => screen.connect()
+        };
+
+        screen
+    }
+
+    /// The connected instance as long as the instance field still points at it, the forms would talk to the wrong
+    /// instance otherwise.
+    pub fn current_instance(&self) -> Option<&Instance> {
+        let url = instance::normalise(&self.instance_url).ok()?;
+
+        self.instance.as_ref().filter(|instance| instance.base_url == url)
+    }
+
+    /// Switches the forms over to whatever is in the instance field once its configuration checks out.
+    pub fn connect(&mut self) {
+        let url = self.instance_url.clone();
+
+        self.instance = None;
+        self.instance_error = None;
+        self.connecting = Some(Request::spawn(async move { instance::connect(&url).await }));
+    }
+}
+
+/// Shown after logging in to an account which hasn't picked a username yet.
+pub struct OnboardingScreen {
+    pub instance: Instance,
+    pub session: Session,
+    pub remember: bool,
+    pub check: Option<Request<Result<bool, String>>>,
+    pub username: String,
+    pub request: Option<Request<Result<(), String>>>,
+    pub error: Option<String>,
+}
+
+pub enum OnboardingOutcome {
+    Done,
+    Cancelled,
+}
+
+pub enum LoginOutcome {
+    LoggedIn(Instance, Credentials, bool),
+    Cancelled,
+}
+
+pub enum Screen {
+    /// Saved accounts' instances are being reached before their sessions can start.
+    Resuming,
+    Login(LoginScreen),
+    Onboarding(OnboardingScreen),
+    /// The active account's client.
+    Client,
+}
+
+/// Background tasks belonging to a signed in account, stopped when it's dropped.
+struct SessionTasks {
+    _actions: TaskHandle,
+    _gateway: TaskHandle,
+}
+
+/// How accounts are saved between runs.
+#[derive(Serialize, Deserialize)]
+struct SavedAccount {
+    instance: String,
+    credentials: Credentials,
+}
+
+/// Everything `accounts.json` has held, older versions kept bare credentials.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAccounts {
+    Current(Vec<SavedAccount>),
+    Legacy(Vec<Credentials>),
+}
+
+/// Saved accounts, migrating from older versions which were always for the default instance.
+fn load_accounts() -> Result<Vec<SavedAccount>, String> {
+    let legacy = |credentials: Vec<Credentials>| credentials.into_iter()
+        .map(|credentials| SavedAccount { instance: DEFAULT_INSTANCE.to_string(), credentials })
+        .collect::<Vec<_>>();
+
+    Ok(match storage::load::<StoredAccounts>(ACCOUNTS_FILE)? {
+        Some(StoredAccounts::Current(saved)) => saved,
+        Some(StoredAccounts::Legacy(credentials)) => legacy(credentials),
+        None => storage::load::<Credentials>(LEGACY_SESSION_FILE)?
+            .map(|credentials| legacy(vec![credentials]))
+            .unwrap_or_default()
+    })
+}
+
+/// A signed in account with its own connection and state.
+pub struct Account {
+    pub id: AccountId,
+    pub instance: Instance,
+    pub state: Box<GlobalState>,
+    credentials: Credentials,
+    remember: bool,
+    _tasks: SessionTasks,
+}
+
+impl Account {
+    /// The account's own name once the gateway has sent it, along with the instance if it's self-hosted.
+    pub fn name(&self) -> String {
+        let name = self.state.user_id.as_ref()
+            .and_then(|id| self.state.users.get(id))
+            .map(|user| format!("{}#{}", user.username, user.discriminator))
+            .unwrap_or_else(|| "Connecting...".to_string());
+
+        if self.instance.base_url == DEFAULT_INSTANCE {
+            return name
+        };
+
+        let host = reqwest::Url::parse(&self.instance.base_url).ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| self.instance.base_url.clone());
+
+        format!("{name} on {host}")
+    }
+
+    pub fn unread_summary(&self) -> UnreadSummary {
+        self.state.channels_unread_summary(self.state.channels.keys())
+    }
+}
+
+/// Everything above a single account: the signed in accounts, which one is showing and the login screens.
+pub struct App {
+    pub screen: Screen,
+    pub accounts: Vec<Account>,
+    pub active: AccountId,
+    /// Instance the login screen starts at, the last one logged in to.
+    instance_url: String,
+    last_instance: Option<Instance>,
+    /// Saved accounts whose instance couldn't be reached, kept so they're tried again next time.
+    unavailable: Vec<SavedAccount>,
+    /// Saved accounts paired with their instance, or why it couldn't be reached.
+    resuming: Option<Request<Vec<(SavedAccount, Result<Instance, String>)>>>,
+    next_account: AccountId,
+    events: Sender<(AccountId, AppEvent)>,
+    /// Problems outside any account, like failing to save, shown by whichever account is active.
+    notifier: Notifier,
+    notices: Receiver<Notification>,
+}
+
+impl App {
+    /// Starts reaching the saved accounts' instances, going to the login screen if there aren't any.
+    pub fn new(events: Sender<(AccountId, AppEvent)>) -> Self {
+        let (notifier, notices) = Notifier::channel();
+
+        let settings = InstanceSettings::load().unwrap_or_else(|e| {
+            notifier.notify(Notification::problem("Couldn't load settings", e));
+            InstanceSettings::default()
+        });
+
+        let mut app = Self {
+            screen: Screen::Resuming,
+            accounts: Vec::new(),
+            active: 0,
+            instance_url: instance::resolve(&settings),
+            last_instance: None,
+            unavailable: Vec::new(),
+            resuming: None,
+            next_account: 0,
+            events,
+            notifier,
+            notices,
+        };
+
+        let saved = load_accounts().unwrap_or_else(|e| {
+            app.notifier.notify(Notification::error("Couldn't load saved accounts", e));
+            Vec::new()
+        });
+
+        if saved.is_empty() {
+            app.screen = Screen::Login(app.login_screen());
+        } else {
+            app.resuming = Some(Request::spawn(connect_saved(saved)));
+        };
+
+        app
+    }
+
+    /// Starts the sessions of saved accounts whose instance was reached, keeping the rest to try again next time.
+    fn resume(&mut self, saved: Vec<(SavedAccount, Result<Instance, String>)>) {
+        let mut problems = Vec::new();
+
+        for (account, instance) in saved {
+            match instance {
+                Ok(instance) => {
+                    self.start_session(instance, account.credentials, true);
+                },
+                Err(e) => {
+                    problems.push(e);
+                    self.unavailable.push(account);
+                }
+            }
+        }
+
+        problems.dedup();
+
+        match self.accounts.first().map(|account| account.id) {
+            Some(id) => {
+                self.switch_to(id);
+
+                if let Some(account) = self.active_account() {
+                    for problem in problems {
+                        account.state.notifications.push(Notification::error("Some accounts couldn't be signed in", problem));
+                    }
+                };
+            },
+            None => {
+                let mut screen = self.login_screen();
+                screen.instance_error = problems.into_iter().next();
+                self.screen = Screen::Login(screen);
+            }
+        };
+    }
+
+    fn login_screen(&self) -> LoginScreen {
+        LoginScreen::new(self.instance_url.clone(), self.last_instance.clone())
+    }
+
+    pub fn active_account(&mut self) -> Option<&mut Account> {
+        self.accounts.iter_mut().find(|account| account.id == self.active)
+    }
+
+    pub fn switch_to(&mut self, id: AccountId) {
+        if self.accounts.iter().any(|account| account.id == id) {
+            self.active = id;
+            self.screen = Screen::Client;
+        };
+    }
+
+    /// Shows the login screen without signing out of anything, cancelling it returns to the active account.
+    pub fn add_account(&mut self) {
+        self.screen = Screen::Login(self.login_screen());
+    }
+
+    pub fn cancel_login(&mut self) {
+        if !self.accounts.is_empty() {
+            self.screen = Screen::Client;
+        };
+    }
+
+    /// Checks whether a new user session still needs onboarding before starting it, bots go straight in.
+    pub fn log_in(&mut self, instance: Instance, credentials: Credentials, remember: bool) {
+        if let Err(e) = InstanceSettings::remember(&instance.base_url) {
+            self.notifier.notify(Notification::problem("Couldn't save recent instances", e));
+        };
+
+        self.instance_url = instance.base_url.clone();
+        self.last_instance = Some(instance.clone());
+
+        let session = match credentials {
+            Credentials::Session(session) => session,
+            bot => return self.enter(instance, bot, remember)
+        };
+
+        let auth = instance.auth.clone();
+        let token = session.token.clone();
+
+        self.screen = Screen::Onboarding(OnboardingScreen {
+            instance,
+            session,
+            remember,
+            check: Some(Request::spawn(async move { auth.needs_onboarding(&token).await })),
+            username: String::new(),
+            request: None,
+            error: None,
+        });
+    }
+
+    pub fn finish_onboarding(&mut self) {
+        let Screen::Onboarding(OnboardingScreen { instance, session, remember, .. }) = std::mem::replace(&mut self.screen, Screen::Client) else {
+            return
+        };
+
+        self.enter(instance, Credentials::Session(session), remember);
+    }
+
+    /// Gives up on onboarding and ends the half set up session.
+    pub fn cancel_onboarding(&mut self) {
+        if let Screen::Onboarding(screen) = &self.screen {
+            end_session(HttpClient::new(screen.instance.base_url.clone(), screen.session.token.clone(), TokenKind::Session), self.notifier.clone());
+        };
+
+        self.screen = Screen::Login(self.login_screen());
+        self.cancel_login();
+    }
+
+    fn enter(&mut self, instance: Instance, credentials: Credentials, remember: bool) {
+        let id = self.start_session(instance, credentials, remember);
+        let saved = self.save_accounts();
+
+        self.switch_to(id);
+
+        if let (Err(e), Some(account)) = (saved, self.active_account()) {
+            account.state.notifications.push(Notification::error("Couldn't save the session, you'll need to log in again next time", e.to_string()));
+        };
+    }
+
+    fn start_session(&mut self, instance: Instance, credentials: Credentials, remember: bool) -> AccountId {
+        let id = self.next_account;
+        self.next_account += 1;
+
+        let events = EventSender::new(id, self.events.clone());
+        let (actions, action_service) = Actions::new();
+        let http = HttpClient::new(instance.base_url.clone(), credentials.token().to_string(), credentials.kind());
+
+        let tasks = SessionTasks {
+            _actions: TaskHandle(tokio::spawn(action_service.run(http.clone(), events.clone()))),
+            _gateway: TaskHandle(tokio::spawn(websocket::run(events, credentials.token().to_string(), instance.config.clone()))),
+        };
+
+        let mut state = GlobalState::new(instance.config.clone(), http, actions);
+
+        if let Credentials::Session(session) = &credentials {
+            state.actions.dispatch(Action::FetchUnreads);
+            state.user_id = Some(session.user_id.clone());
+        };
+
+        self.accounts.push(Account { id, instance, state: Box::new(state), credentials, remember, _tasks: tasks });
+
+        id
+    }
+
+    fn save_accounts(&self) -> std::io::Result<()> {
+        let remembered = self.accounts.iter()
+            .filter(|account| account.remember)
+            .map(|account| SavedAccount { instance: account.instance.base_url.clone(), credentials: account.credentials.clone() })
+            .collect::<Vec<_>>();
+
+        let saved = self.unavailable.iter().chain(&remembered).collect::<Vec<_>>();
+
+        storage::save_private(ACCOUNTS_FILE, &saved)?;
+        storage::remove(LEGACY_SESSION_FILE)
+    }
+
+    /// Stops the account and forgets it, handing it back so the caller can decide what to do with its session.
+    fn remove_account(&mut self, id: AccountId, gl: &glow::Context) -> Option<Account> {
+        let index = self.accounts.iter().position(|account| account.id == id)?;
+        let mut account = self.accounts.remove(index);
+
+        account.state.images.destroy(gl);
+
+        if let Err(e) = self.save_accounts() {
+            self.notifier.notify(Notification::error("Couldn't save accounts", e.to_string()));
+        };
+
+        if let Some(next) = self.accounts.first().map(|account| account.id).filter(|_| id == self.active) {
+            self.active = next;
+        };
+
+        Some(account)
+    }
+
+    /// Ends the active account's session on the server and forgets it, moving to the next account if there is one.
+    pub fn log_out(&mut self, gl: &glow::Context) {
+        let Some(account) = self.remove_account(self.active, gl) else { return };
+
+        // Bot tokens aren't sessions, there's nothing to end on the server.
+        if let Credentials::Session(session) = &account.credentials {
+            end_session(HttpClient::new(account.instance.base_url.clone(), session.token.clone(), TokenKind::Session), self.notifier.clone());
+        };
+
+        match self.accounts.first().map(|account| account.id) {
+            Some(next) => self.switch_to(next),
+            None => self.screen = Screen::Login(self.login_screen())
+        };
+    }
+
+    /// Forgets accounts whose session stopped working, going back to the login screen if it was the one showing.
+    fn sign_out_invalid_sessions(&mut self, gl: &glow::Context) {
+        let signed_out = self.accounts.iter()
+            .filter_map(|account| account.state.signed_out.clone().map(|reason| (account.id, reason)))
+            .collect::<Vec<_>>();
+
+        for (id, reason) in signed_out {
+            let was_active = id == self.active && matches!(self.screen, Screen::Client);
+            let Some(account) = self.remove_account(id, gl) else { continue };
+            let message = match account.state.user_id.as_ref().is_some_and(|id| account.state.users.contains_key(id)) {
+                true => format!("{} was signed out", account.name()),
+                false => "An account was signed out".to_string()
+            };
+
+            if was_active {
+                let mut screen = LoginScreen::new(account.instance.base_url.clone(), Some(account.instance.clone()));
+                screen.error = Some(format!("{message}: {reason}"));
+                self.screen = Screen::Login(screen);
+            } else {
+                self.notifier.notify(Notification::error(message, reason));
+            };
+        }
+    }
+
+    /// Keeps accounts which aren't on screen up to date, called once per frame on the thread owning the gl context.
+    pub fn update(&mut self, gl: &glow::Context) {
+        if let Some(saved) = self.resuming.as_ref().and_then(Request::poll) {
+            self.resuming = None;
+            self.resume(saved);
+        };
+
+        self.sign_out_invalid_sessions(gl);
+
+        for account in &mut self.accounts {
+            account.state.flush_acks();
+            account.state.notifications.update();
+        }
+
+        // Kept until there's an account to show them.
+        if let Some(account) = self.accounts.iter_mut().find(|account| account.id == self.active) {
+            while let Ok(notice) = self.notices.try_recv() {
+                account.state.notifications.push(notice);
+            }
+        };
+    }
+}
+
+/// Connects to each saved account's instance once, all at the same time.
+async fn connect_saved(saved: Vec<SavedAccount>) -> Vec<(SavedAccount, Result<Instance, String>)> {
+    let mut urls = saved.iter().map(|account| account.instance.clone()).collect::<Vec<_>>();
+    urls.sort();
+    urls.dedup();
+
+    let connected = futures::future::join_all(urls.iter().map(|url| instance::connect(url))).await;
+    let instances = urls.into_iter().zip(connected).collect::<HashMap<_, _>>();
+
+    saved.into_iter()
+        .map(|account| {
+            let instance = instances[&account.instance].clone();
+            (account, instance)
+        })
+        .collect()
+}
+
+/// Logs the session out in the background, its action service is about to be stopped so this can't go through it.
+fn end_session(http: HttpClient, notifier: Notifier) {
+    tokio::spawn(async move {
+        if let Err(e) = http.log_out().await {
+            notifier.notify(Notification::problem("Couldn't end the session", e.to_string()));
+        }
+    });
+}
+
+pub fn update_app((account_id, event): (AccountId, AppEvent), app: &mut App) {
+    // Events from accounts which have since logged out are dropped.
+    if let Some(account) = app.accounts.iter_mut().find(|account| account.id == account_id) {
+        update_state(event, &mut account.state)
+    };
+}
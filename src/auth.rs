@@ -0,0 +1,479 @@
+use reqwest::{Client, Method, RequestBuilder, Response};
+use revolt_models::v0::User;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{http::TokenKind, instance};
+
+/// Second factor sent back with the ticket from [`LoginResponse::Mfa`].
+#[derive(Serialize, Clone)]
+#[serde(untagged)]
+pub enum MfaResponse {
+    Totp { totp_code: String },
+    Recovery { recovery_code: String },
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MfaMethod {
+    Password,
+    Recovery,
+    Totp,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(untagged)]
+pub enum LoginRequest {
+    Email {
+        email: String,
+        password: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        captcha: Option<String>,
+        friendly_name: String,
+    },
+    Mfa {
+        mfa_ticket: String,
+        mfa_response: MfaResponse,
+        friendly_name: String,
+    },
+}
+
+/// A logged in session. Deliberately not `Debug` so the token can't end up in logs.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Session {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub user_id: String,
+    pub token: String,
+    pub name: String,
+}
+
+/// What we authenticate with, saved between runs when the user wants to stay logged in.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Credentials {
+    Session(Session),
+    Bot { bot_token: String },
+}
+
+impl Credentials {
+    pub fn token(&self) -> &str {
+        match self {
+            Credentials::Session(session) => &session.token,
+            Credentials::Bot { bot_token } => bot_token,
+        }
+    }
+
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Credentials::Session(_) => TokenKind::Session,
+            Credentials::Bot { .. } => TokenKind::Bot,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "result")]
+pub enum LoginResponse {
+    Success(Session),
+    #[serde(rename = "MFA")]
+    Mfa { ticket: String, allowed_methods: Vec<MfaMethod> },
+    Disabled { user_id: String },
+}
+
+#[derive(Serialize)]
+pub struct CreateAccount {
+    pub email: String,
+    pub password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invite: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captcha: Option<String>,
+}
+
+/// Body for both resending the verification email and requesting a password reset.
+#[derive(Serialize)]
+pub struct EmailRequest {
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captcha: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PasswordReset {
+    pub token: String,
+    pub password: String,
+    pub remove_sessions: bool,
+}
+
+#[derive(Deserialize)]
+struct OnboardingStatus {
+    onboarding: bool,
+}
+
+#[derive(Serialize)]
+struct CompleteOnboarding<'a> {
+    username: &'a str,
+}
+
+/// Codes are sent as links in emails, accept either the whole link or just the code on the end.
+pub fn code_from_link(input: &str) -> String {
+    input.trim().trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string()
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// Turns Revolt's error types into something worth showing on the login screen.
+fn describe_error(kind: &str) -> String {
+    match kind {
+        "InvalidCredentials" => "Incorrect email or password".to_string(),
+        "InvalidSession" => "That token isn't valid".to_string(),
+        "InvalidToken" => "That code isn't valid".to_string(),
+        "CaptchaFailed" => "The captcha wasn't accepted, try again".to_string(),
+        "UnverifiedAccount" => "Verify your email address before logging in".to_string(),
+        "LockedOut" => "Too many failed attempts, try again later".to_string(),
+        "DisallowedMFAMethod" => "That verification method isn't enabled for this account".to_string(),
+        "EmailInUse" => "An account already uses that email address".to_string(),
+        "InvalidInvite" | "MissingInvite" => "That invite code isn't valid".to_string(),
+        "CompromisedPassword" => "That password has appeared in a data breach, pick another".to_string(),
+        "ShortPassword" => "That password is too short".to_string(),
+        "InvalidEmail" | "Blacklisted" => "That email address can't be used".to_string(),
+        "UnknownUser" => "No account uses that email address".to_string(),
+        "UsernameTaken" => "That username is taken".to_string(),
+        "InvalidUsername" => "That username isn't allowed".to_string(),
+        kind => format!("The server returned {kind}"),
+    }
+}
+
+/// Unauthenticated requests to the `/auth` routes.
+#[derive(Clone)]
+pub struct AuthClient {
+    pub base: String,
+    inner: Client,
+}
+
+impl AuthClient {
+    pub fn new(base: String) -> Self {
+        AuthClient { base, inner: instance::client() }
+    }
+
+    async fn send(&self, builder: RequestBuilder) -> Result<Response, String> {
+        let response = builder
+            .send().await
+            .map_err(|e| format!("Couldn't reach the server: {e}"))?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response)
+        };
+
+        if status.as_u16() == 429 {
+            return Err("You're doing that too often, wait a moment and try again".to_string())
+        };
+
+        match response.json::<ApiError>().await {
+            Ok(error) => Err(describe_error(&error.kind)),
+            Err(_) => Err(format!("The server returned {status}"))
+        }
+    }
+
+    fn builder<I: Serialize>(&self, method: Method, route: &str, body: &I) -> RequestBuilder {
+        self.inner.request(method, format!("{}{route}", &self.base)).json(body)
+    }
+
+    async fn post<I: Serialize, O: DeserializeOwned>(&self, route: &str, body: &I) -> Result<O, String> {
+        self.send(self.builder(Method::POST, route, body)).await?
+            .json().await
+            .map_err(|e| format!("Couldn't read the server's response: {e}"))
+    }
+
+    async fn request_empty<I: Serialize>(&self, method: Method, route: &str, body: &I) -> Result<(), String> {
+        self.send(self.builder(method, route, body)).await.map(|_| ())
+    }
+
+    pub async fn login(&self, request: LoginRequest) -> Result<LoginResponse, String> {
+        self.post("/auth/session/login", &request).await
+    }
+
+    pub async fn create_account(&self, body: CreateAccount) -> Result<(), String> {
+        self.request_empty(Method::POST, "/auth/account/create", &body).await
+    }
+
+    pub async fn resend_verification(&self, body: EmailRequest) -> Result<(), String> {
+        self.request_empty(Method::POST, "/auth/account/reverify", &body).await
+    }
+
+    pub async fn verify_email(&self, code: &str) -> Result<(), String> {
+        self.request_empty(Method::POST, &format!("/auth/account/verify/{code}"), &()).await
+    }
+
+    pub async fn request_password_reset(&self, body: EmailRequest) -> Result<(), String> {
+        self.request_empty(Method::POST, "/auth/account/reset_password", &body).await
+    }
+
+    pub async fn reset_password(&self, body: PasswordReset) -> Result<(), String> {
+        self.request_empty(Method::PATCH, "/auth/account/reset_password", &body).await
+    }
+
+    /// Makes sure a pasted bot token works before connecting with it.
+    pub async fn check_bot_token(&self, token: &str) -> Result<(), String> {
+        let builder = self.inner.get(format!("{}/users/@me", &self.base)).header(TokenKind::Bot.header(), token);
+
+        let user = self.send(builder).await?
+            .json::<User>().await
+            .map_err(|e| format!("Couldn't read the server's response: {e}"))?;
+
+        match user.bot {
+            Some(_) => Ok(()),
+            None => Err("That token doesn't belong to a bot".to_string())
+        }
+    }
+
+    /// Whether the account still needs to pick a username.
+    pub async fn needs_onboarding(&self, token: &str) -> Result<bool, String> {
+        let response = self.send(self.inner.get(format!("{}/onboard/hello", &self.base)).header(TokenKind::Session.header(), token)).await?;
+
+        response.json::<OnboardingStatus>().await
+            .map(|status| status.onboarding)
+            .map_err(|e| format!("Couldn't read the server's response: {e}"))
+    }
+
+    pub async fn complete_onboarding(&self, token: &str, username: &str) -> Result<(), String> {
+        let builder = self.builder(Method::POST, "/onboard/complete", &CompleteOnboarding { username })
+            .header(TokenKind::Session.header(), token);
+
+        self.send(builder).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mock_server::MockServer;
+
+    fn client(server: &MockServer) -> AuthClient {
+        AuthClient::new(server.base.clone())
+    }
+
+    #[test]
+    fn email_login_body() {
+        let request = LoginRequest::Email {
+            email: "user@example.com".to_string(),
+            password: "hunter22".to_string(),
+            captcha: None,
+            friendly_name: "ImRevolt".to_string(),
+        };
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), json!({
+            "email": "user@example.com",
+            "password": "hunter22",
+            "friendly_name": "ImRevolt",
+        }));
+    }
+
+    #[test]
+    fn mfa_login_body() {
+        let totp = LoginRequest::Mfa {
+            mfa_ticket: "ticket".to_string(),
+            mfa_response: MfaResponse::Totp { totp_code: "123456".to_string() },
+            friendly_name: "ImRevolt".to_string(),
+        };
+
+        assert_eq!(serde_json::to_value(&totp).unwrap(), json!({
+            "mfa_ticket": "ticket",
+            "mfa_response": { "totp_code": "123456" },
+            "friendly_name": "ImRevolt",
+        }));
+
+        let recovery = MfaResponse::Recovery { recovery_code: "abcd-efgh".to_string() };
+
+        assert_eq!(serde_json::to_value(&recovery).unwrap(), json!({ "recovery_code": "abcd-efgh" }));
+    }
+
+    #[tokio::test]
+    async fn login_with_mfa() {
+        let server = MockServer::start(vec![(200, r#"{"result":"MFA","ticket":"ticket","allowed_methods":["Totp","Recovery"]}"#)]);
+
+        let request = LoginRequest::Email {
+            email: "user@example.com".to_string(),
+            password: "hunter22".to_string(),
+            captcha: Some("captcha".to_string()),
+            friendly_name: "ImRevolt".to_string(),
+        };
+
+        match client(&server).login(request).await {
+            Ok(LoginResponse::Mfa { ticket, allowed_methods }) => {
+                assert_eq!(ticket, "ticket");
+                assert_eq!(allowed_methods, vec![MfaMethod::Totp, MfaMethod::Recovery]);
+            },
+            _ => panic!("expected an MFA challenge")
+        };
+
+        let request = server.request();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/auth/session/login");
+        assert_eq!(request.json()["captcha"], "captcha");
+    }
+
+    #[tokio::test]
+    async fn login_error_is_described() {
+        let server = MockServer::start(vec![(401, r#"{"type":"InvalidCredentials"}"#), (429, "{}")]);
+        let auth = client(&server);
+
+        let request = LoginRequest::Email {
+            email: "user@example.com".to_string(),
+            password: "wrong".to_string(),
+            captcha: None,
+            friendly_name: "ImRevolt".to_string(),
+        };
+
+        assert_eq!(auth.login(request.clone()).await.err().as_deref(), Some("Incorrect email or password"));
+        assert_eq!(auth.login(request).await.err().as_deref(), Some("You're doing that too often, wait a moment and try again"));
+    }
+
+    #[tokio::test]
+    async fn create_account() {
+        let server = MockServer::start(vec![(204, "")]);
+
+        let body = CreateAccount {
+            email: "user@example.com".to_string(),
+            password: "hunter22".to_string(),
+            invite: Some("invite".to_string()),
+            captcha: None,
+        };
+
+        client(&server).create_account(body).await.unwrap();
+
+        let request = server.request();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/auth/account/create");
+        assert_eq!(request.json(), json!({ "email": "user@example.com", "password": "hunter22", "invite": "invite" }));
+    }
+
+    #[tokio::test]
+    async fn create_account_email_in_use() {
+        let server = MockServer::start(vec![(409, r#"{"type":"EmailInUse"}"#)]);
+
+        let body = CreateAccount { email: "user@example.com".to_string(), password: "hunter22".to_string(), invite: None, captcha: None };
+
+        assert_eq!(client(&server).create_account(body).await.err().as_deref(), Some("An account already uses that email address"));
+    }
+
+    #[tokio::test]
+    async fn resend_verification() {
+        let server = MockServer::start(vec![(204, "")]);
+
+        let body = EmailRequest { email: "user@example.com".to_string(), captcha: Some("captcha".to_string()) };
+
+        client(&server).resend_verification(body).await.unwrap();
+
+        let request = server.request();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/auth/account/reverify");
+        assert_eq!(request.json(), json!({ "email": "user@example.com", "captcha": "captcha" }));
+    }
+
+    #[tokio::test]
+    async fn verify_email() {
+        let server = MockServer::start(vec![(200, r#"{"ticket":null}"#)]);
+
+        client(&server).verify_email("code").await.unwrap();
+
+        let request = server.request();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/auth/account/verify/code");
+    }
+
+    #[tokio::test]
+    async fn request_password_reset() {
+        let server = MockServer::start(vec![(204, "")]);
+
+        let body = EmailRequest { email: "user@example.com".to_string(), captcha: None };
+
+        client(&server).request_password_reset(body).await.unwrap();
+
+        let request = server.request();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/auth/account/reset_password");
+        assert_eq!(request.json(), json!({ "email": "user@example.com" }));
+    }
+
+    #[tokio::test]
+    async fn reset_password() {
+        let server = MockServer::start(vec![(204, "")]);
+
+        let body = PasswordReset { token: "token".to_string(), password: "hunter22".to_string(), remove_sessions: true };
+
+        client(&server).reset_password(body).await.unwrap();
+
+        let request = server.request();
+
+        assert_eq!(request.method, "PATCH");
+        assert_eq!(request.path, "/auth/account/reset_password");
+        assert_eq!(request.json(), json!({ "token": "token", "password": "hunter22", "remove_sessions": true }));
+    }
+
+    #[tokio::test]
+    async fn reset_password_bad_token() {
+        let server = MockServer::start(vec![(401, r#"{"type":"InvalidToken"}"#)]);
+
+        let body = PasswordReset { token: "token".to_string(), password: "hunter22".to_string(), remove_sessions: false };
+
+        assert_eq!(client(&server).reset_password(body).await.err().as_deref(), Some("That code isn't valid"));
+    }
+
+    #[tokio::test]
+    async fn needs_onboarding() {
+        let server = MockServer::start(vec![(200, r#"{"onboarding":true}"#), (200, r#"{"onboarding":false}"#)]);
+        let auth = client(&server);
+
+        assert_eq!(auth.needs_onboarding("token").await, Ok(true));
+        assert_eq!(auth.needs_onboarding("token").await, Ok(false));
+
+        for request in server.requests() {
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.path, "/onboard/hello");
+            assert_eq!(request.header("x-session-token"), Some("token"));
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_onboarding() {
+        let server = MockServer::start(vec![(200, "{}")]);
+
+        client(&server).complete_onboarding("token", "username").await.unwrap();
+
+        let request = server.request();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/onboard/complete");
+        assert_eq!(request.header("x-session-token"), Some("token"));
+        assert_eq!(request.json(), json!({ "username": "username" }));
+    }
+
+    #[tokio::test]
+    async fn complete_onboarding_username_taken() {
+        let server = MockServer::start(vec![(409, r#"{"type":"UsernameTaken"}"#)]);
+
+        assert_eq!(client(&server).complete_onboarding("token", "username").await.err().as_deref(), Some("That username is taken"));
+    }
+
+    #[test]
+    fn code_from_links() {
+        assert_eq!(code_from_link("abc123"), "abc123");
+        assert_eq!(code_from_link("  abc123  "), "abc123");
+        assert_eq!(code_from_link("https://app.revolt.chat/login/verify/abc123"), "abc123");
+        assert_eq!(code_from_link("https://app.revolt.chat/login/reset/abc123/"), "abc123");
+        assert_eq!(code_from_link(""), "");
+    }
+}
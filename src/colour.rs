@@ -0,0 +1,108 @@
+/// Parses a css hex colour (`#rgb`, `#rrggbb` or `#rrggbbaa`) into an imgui colour.
+pub fn parse_colour(colour: &str) -> Option<[f32; 4]> {
+    let hex = colour.trim().strip_prefix('#').filter(|hex| hex.is_ascii())?;
+
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok().map(|v| v as f32 / 255.0);
+
+    match hex.len() {
+        3 => {
+            let mut rgb = hex.chars().map(|c| channel(&c.to_string().repeat(2)));
+
+            Some([rgb.next()??, rgb.next()??, rgb.next()??, 1.0])
+        },
+        6 => Some([channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?, 1.0]),
+        8 => Some([channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?, channel(&hex[6..8])?]),
+        _ => None
+    }
+}
+
+fn named_colour(name: &str) -> Option<[f32; 4]> {
+    let rgb = match name.to_ascii_lowercase().as_str() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 128, 0],
+        "lime" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "orange" => [255, 165, 0],
+        "purple" => [128, 0, 128],
+        "pink" => [255, 192, 203],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gold" => [255, 215, 0],
+        "grey" | "gray" => [128, 128, 128],
+        _ => return None
+    };
+
+    Some([rgb[0] as f32 / 255.0, rgb[1] as f32 / 255.0, rgb[2] as f32 / 255.0, 1.0])
+}
+
+/// Parses `rgb(r, g, b)` and `rgba(r, g, b, a)`.
+fn parse_rgb(colour: &str) -> Option<[f32; 4]> {
+    let args = colour.strip_prefix("rgba(").or_else(|| colour.strip_prefix("rgb("))?.strip_suffix(')')?;
+    let mut parts = args.split(|c| c == ',' || c == ' ' || c == '/').filter(|p| !p.is_empty());
+
+    let mut channel = || parts.next()?.trim().parse::<f32>().ok();
+
+    let (r, g, b) = (channel()?, channel()?, channel()?);
+    let a = channel().unwrap_or(1.0);
+
+    Some([r / 255.0, g / 255.0, b / 255.0, a])
+}
+
+/// Parses any css colour value a role or masquerade can hold.
+///
+/// Gradients can't be drawn as text colours so they're approximated by the average of their stops.
+pub fn parse_css_colour(colour: &str) -> Option<[f32; 4]> {
+    let colour = colour.trim();
+
+    if let Some(start) = colour.find("gradient(") {
+        let mut stops = Vec::new();
+        let mut rest = &colour[start + "gradient(".len()..];
+
+        loop {
+            let hex = rest.find('#');
+            let rgb = rest.find("rgb");
+
+            let (end, stop) = match (hex, rgb) {
+                (Some(hex), rgb) if rgb.map_or(true, |rgb| hex < rgb) => {
+                    let end = rest[hex + 1..]
+                        .find(|c: char| !c.is_ascii_hexdigit())
+                        .map_or(rest.len(), |end| hex + 1 + end);
+
+                    (end, parse_colour(&rest[hex..end]))
+                },
+                (_, Some(rgb)) => {
+                    let Some(end) = rest[rgb..].find(')').map(|end| rgb + end + 1) else { break };
+
+                    (end, parse_rgb(&rest[rgb..end]))
+                },
+                _ => break
+            };
+
+            stops.extend(stop);
+            rest = &rest[end..];
+        }
+
+        if stops.is_empty() {
+            return None
+        };
+
+        let count = stops.len() as f32;
+
+        return Some(stops.into_iter().fold([0.0; 4], |acc, stop| {
+            [acc[0] + stop[0] / count, acc[1] + stop[1] / count, acc[2] + stop[2] / count, acc[3] + stop[3] / count]
+        }))
+    };
+
+    parse_colour(colour)
+        .or_else(|| parse_rgb(colour))
+        .or_else(|| named_colour(colour))
+}
+
+pub fn to_hex(colour: [f32; 3]) -> String {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    format!("#{:02x}{:02x}{:02x}", channel(colour[0]), channel(colour[1]), channel(colour[2]))
+}
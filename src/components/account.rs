@@ -0,0 +1,279 @@
+use std::future::Future;
+
+use imgui::Ui;
+
+use crate::{app::{AuthPage, LoginScreen, OnboardingOutcome, OnboardingScreen}, auth::{code_from_link, AuthClient, CreateAccount, EmailRequest, PasswordReset}, components::{bot_token_form, centered_window}, http::RevoltConfig, state::Request};
+
+const ERROR_COLOUR: [f32; 4] = [0.9, 0.3, 0.3, 1.0];
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Runs one of the account requests, moving to `next` and showing `notice` once it succeeds.
+fn submit(screen: &mut LoginScreen, next: AuthPage, notice: &'static str, future: impl Future<Output = Result<(), String>> + Send + 'static) {
+    screen.error = None;
+    screen.notice = None;
+    screen.pending = Some(Request::spawn(async move { future.await.map(|_| (next, notice)) }));
+}
+
+/// Shows the captcha field when the instance wants one, returns the token to send if it's been filled in.
+pub fn captcha_field(ui: &Ui, screen: &mut LoginScreen, config: &RevoltConfig) -> Option<Option<String>> {
+    if !config.features.captcha.enabled {
+        return Some(None)
+    };
+
+    ui.separator();
+    ui.text_wrapped("This instance requires a captcha. Solve the hCaptcha for the site key below in a browser and paste the response token here.");
+    ui.text_disabled(&config.features.captcha.key);
+    ui.input_text("Captcha", &mut screen.captcha).build();
+
+    let captcha = screen.captcha.trim();
+
+    (!captcha.is_empty()).then(|| Some(captcha.to_string()))
+}
+
+fn register(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient, config: &RevoltConfig) {
+    ui.input_text("Email", &mut screen.email).build();
+    ui.input_text("Password", &mut screen.password).password(true).build();
+
+    if config.features.invite_only {
+        ui.input_text("Invite Code", &mut screen.invite).build();
+        ui.text_disabled("This instance is invite only.");
+    };
+
+    let captcha = captcha_field(ui, screen, config);
+
+    let short_password = !screen.password.is_empty() && screen.password.chars().count() < MIN_PASSWORD_LENGTH;
+
+    if short_password {
+        ui.text_disabled(format!("Passwords need at least {MIN_PASSWORD_LENGTH} characters."));
+    };
+
+    let invite = screen.invite.trim().to_string();
+    let ready = !screen.email.trim().is_empty() && !screen.password.is_empty() && !short_password
+        && (!config.features.invite_only || !invite.is_empty());
+
+    ui.disabled(!ready || captcha.is_none(), || {
+        if ui.button("Create Account") {
+            let auth = auth.clone();
+            let body = CreateAccount {
+                email: screen.email.trim().to_string(),
+                password: screen.password.clone(),
+                invite: config.features.invite_only.then_some(invite),
+                captcha: captcha.flatten(),
+            };
+
+            // Without email verification the account can log in straight away.
+            let (next, notice) = if config.features.email {
+                (AuthPage::Verify, "Account created, check your email for a verification link")
+            } else {
+                (AuthPage::Login, "Account created, you can log in now")
+            };
+
+            submit(screen, next, notice, async move { auth.create_account(body).await });
+        };
+    });
+}
+
+fn verify(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient) {
+    ui.text_wrapped("Paste the verification link or code from the email we sent you.");
+    ui.input_text("Code", &mut screen.code).build();
+
+    let code = code_from_link(&screen.code);
+
+    ui.disabled(code.is_empty(), || {
+        if ui.button("Verify") {
+            let auth = auth.clone();
+
+            submit(screen, AuthPage::Login, "Email verified, you can log in now", async move { auth.verify_email(&code).await });
+        };
+    });
+
+    ui.same_line();
+
+    if ui.button("Resend Email") {
+        screen.page = AuthPage::ResendVerification;
+    };
+}
+
+fn resend_verification(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient, config: &RevoltConfig) {
+    ui.input_text("Email", &mut screen.email).build();
+
+    let captcha = captcha_field(ui, screen, config);
+
+    ui.disabled(screen.email.trim().is_empty() || captcha.is_none(), || {
+        if ui.button("Resend") {
+            let auth = auth.clone();
+            let body = EmailRequest { email: screen.email.trim().to_string(), captcha: captcha.flatten() };
+
+            submit(screen, AuthPage::Verify, "Sent, check your email for a new verification link", async move { auth.resend_verification(body).await });
+        };
+    });
+}
+
+fn request_reset(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient, config: &RevoltConfig) {
+    ui.input_text("Email", &mut screen.email).build();
+
+    let captcha = captcha_field(ui, screen, config);
+
+    ui.disabled(screen.email.trim().is_empty() || captcha.is_none(), || {
+        if ui.button("Send Reset Link") {
+            let auth = auth.clone();
+            let body = EmailRequest { email: screen.email.trim().to_string(), captcha: captcha.flatten() };
+
+            submit(screen, AuthPage::ConfirmReset, "Sent, check your email for a reset link", async move { auth.request_password_reset(body).await });
+        };
+    });
+
+    ui.same_line();
+
+    if ui.button("I Have a Link") {
+        screen.page = AuthPage::ConfirmReset;
+    };
+}
+
+fn confirm_reset(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient) {
+    ui.text_wrapped("Paste the reset link or token from the email and choose a new password.");
+    ui.input_text("Token", &mut screen.code).build();
+    ui.input_text("New Password", &mut screen.new_password).password(true).build();
+    ui.checkbox("Log out of all other sessions", &mut screen.log_out_other_sessions);
+
+    let token = code_from_link(&screen.code);
+    let short_password = screen.new_password.chars().count() < MIN_PASSWORD_LENGTH;
+
+    if !screen.new_password.is_empty() && short_password {
+        ui.text_disabled(format!("Passwords need at least {MIN_PASSWORD_LENGTH} characters."));
+    };
+
+    ui.disabled(token.is_empty() || short_password, || {
+        if ui.button("Reset Password") {
+            let auth = auth.clone();
+            let body = PasswordReset {
+                token,
+                password: std::mem::take(&mut screen.new_password),
+                remove_sessions: screen.log_out_other_sessions,
+            };
+
+            submit(screen, AuthPage::Login, "Password changed, you can log in now", async move { auth.reset_password(body).await });
+        };
+    });
+}
+
+/// Contents of every page except the login form itself.
+pub fn account_page(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient, config: &RevoltConfig) {
+    match screen.page {
+        AuthPage::Login => {},
+        AuthPage::BotToken => bot_token_form(ui, screen, auth),
+        AuthPage::Register => register(ui, screen, auth, config),
+        AuthPage::Verify => verify(ui, screen, auth),
+        AuthPage::ResendVerification => resend_verification(ui, screen, auth, config),
+        AuthPage::RequestReset => request_reset(ui, screen, auth, config),
+        AuthPage::ConfirmReset => confirm_reset(ui, screen, auth),
+    }
+}
+
+/// Applies the result of the last account request, called before drawing the login screen.
+pub fn poll_account_request(screen: &mut LoginScreen) {
+    let Some(result) = screen.pending.as_ref().and_then(Request::poll) else { return };
+
+    screen.pending = None;
+
+    match result {
+        Ok((next, notice)) => {
+            screen.page = next;
+            screen.notice = Some(notice);
+            screen.captcha.clear();
+        },
+        Err(e) => screen.error = Some(e)
+    }
+}
+
+fn valid_username(username: &str) -> bool {
+    (2..=32).contains(&username.chars().count())
+        && username.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Picks a username for a new account, returns once it's done or the user gives up.
+pub fn onboarding(ui: &Ui, screen: &mut OnboardingScreen) -> Option<OnboardingOutcome> {
+    let mut outcome = None;
+    let auth = screen.instance.auth.clone();
+
+    if let Some(result) = screen.check.as_ref().and_then(Request::poll) {
+        screen.check = None;
+
+        match result {
+            Ok(true) => {},
+            Ok(false) => return Some(OnboardingOutcome::Done),
+            Err(e) => screen.error = Some(e)
+        }
+    };
+
+    if let Some(result) = screen.request.as_ref().and_then(Request::poll) {
+        screen.request = None;
+
+        match result {
+            Ok(()) => return Some(OnboardingOutcome::Done),
+            Err(e) => screen.error = Some(e)
+        }
+    };
+
+    centered_window(ui, "Welcome", || {
+        if screen.check.is_some() {
+            ui.text_disabled("Checking your account...");
+            return
+        };
+
+        ui.text("Pick a username to finish setting up your account.");
+        ui.text_disabled("You can change it later.");
+
+        let entered = ui.input_text("Username", &mut screen.username)
+            .enter_returns_true(true)
+            .build();
+
+        let username = screen.username.trim().to_string();
+        let valid = valid_username(&username);
+
+        if !username.is_empty() && !valid {
+            ui.text_disabled("Usernames are 2 to 32 letters, numbers, dots, dashes or underscores.");
+        };
+
+        ui.disabled(!valid || screen.request.is_some(), || {
+            if ui.button("Continue") || (entered && valid) {
+                let auth = auth.clone();
+                let token = screen.session.token.clone();
+
+                screen.error = None;
+                screen.request = Some(Request::spawn(async move { auth.complete_onboarding(&token, &username).await }));
+            };
+        });
+
+        ui.same_line();
+
+        if ui.button("Log Out") {
+            outcome = Some(OnboardingOutcome::Cancelled);
+        };
+
+        if let Some(error) = &screen.error {
+            ui.text_colored(ERROR_COLOUR, error);
+        };
+    });
+
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::valid_username;
+
+    #[test]
+    fn usernames() {
+        assert!(valid_username("ab"));
+        assert!(valid_username("user_name.with-dots"));
+        assert!(valid_username("ünïcode"));
+        assert!(valid_username(&"a".repeat(32)));
+
+        assert!(!valid_username(""));
+        assert!(!valid_username("a"));
+        assert!(!valid_username(&"a".repeat(33)));
+        assert!(!valid_username("has space"));
+        assert!(!valid_username("at@sign"));
+    }
+}
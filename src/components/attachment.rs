@@ -0,0 +1,130 @@
+use imgui::{Condition, Ui};
+use revolt_models::v0::{File, Metadata};
+
+use crate::{components::image, images, notifications::{Notification, Notifier}, state::GlobalState};
+
+const THUMBNAIL_SIZE: f32 = 300.0;
+
+pub fn format_size(size: isize) -> String {
+    let size = size as f64;
+
+    if size < 1024.0 {
+        format!("{size} B")
+    } else if size < 1024.0 * 1024.0 {
+        format!("{:.1} KB", size / 1024.0)
+    } else if size < 1024.0 * 1024.0 * 1024.0 {
+        format!("{:.1} MB", size / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} GB", size / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+fn fit(width: f32, height: f32, max: [f32; 2]) -> [f32; 2] {
+    let scale = (max[0] / width).min(max[1] / height).min(1.0);
+
+    [width * scale, height * scale]
+}
+
+fn save_as(notifier: Notifier, url: String, filename: String) {
+    tokio::spawn(async move {
+        let Some(handle) = rfd::AsyncFileDialog::new()
+            .set_file_name(&filename)
+            .save_file()
+            .await else { return };
+
+        let notification = match images::download(&reqwest::Client::new(), &url).await {
+            Ok(bytes) => match std::fs::write(handle.path(), bytes) {
+                Ok(()) => Notification::success(format!("Saved {filename}")),
+                Err(e) => Notification::error(format!("Couldn't save {filename}"), e.to_string())
+            },
+            Err(e) => Notification::error(format!("Couldn't download {filename}"), e.to_string())
+        };
+
+        notifier.notify(notification);
+    });
+}
+
+pub fn attachment(ui: &Ui, state: &mut GlobalState, file: &File) {
+    let url = state.config.autumn_url(file);
+    let _id = ui.push_id(&file.id);
+
+    ui.group(|| {
+        if let Metadata::Image { width, height } = &file.metadata {
+            let size = fit(*width as f32, *height as f32, [THUMBNAIL_SIZE, THUMBNAIL_SIZE]);
+
+            image(ui, state, file, size);
+
+            if ui.is_item_clicked() {
+                state.viewed_attachment = Some(file.clone());
+            };
+
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Click to open");
+            };
+        };
+
+        ui.text(&file.filename);
+        ui.text_disabled(format!("{} - {}", format_size(file.size), file.content_type));
+
+        match &file.metadata {
+            Metadata::Image { width, height } | Metadata::Video { width, height } => {
+                ui.same_line();
+                ui.text_disabled(format!("- {width}x{height}"));
+            },
+            _ => {}
+        };
+
+        if ui.small_button("Save As...") {
+            save_as(state.notifications.notifier(), url.clone(), file.filename.clone());
+        };
+    });
+
+    let draw_list = ui.get_window_draw_list();
+    let (min, max) = (ui.item_rect_min(), ui.item_rect_max());
+
+    draw_list
+        .add_rect([min[0] - 4.0, min[1] - 4.0], [max[0] + 4.0, max[1] + 4.0], [0.4, 0.4, 0.4, 1.0])
+        .rounding(4.0)
+        .build();
+
+    ui.dummy([0.0, 4.0]);
+}
+
+pub fn attachment_viewer(ui: &Ui, state: &mut GlobalState) {
+    let Some(file) = state.viewed_attachment.clone() else { return };
+
+    let mut opened = true;
+
+    ui.window(format!("{}###AttachmentViewer", file.filename))
+        .opened(&mut opened)
+        .size([800.0, 600.0], Condition::FirstUseEver)
+        .horizontal_scrollbar(true)
+        .build(|| {
+            let url = state.config.autumn_url(&file);
+
+            if ui.button("Save As...") {
+                save_as(state.notifications.notifier(), url.clone(), file.filename.clone());
+            };
+
+            ui.same_line();
+            ui.text_disabled(format_size(file.size));
+
+            let key = format!("{}:full", file.id);
+
+            if let Some(texture) = state.images.get(&key, &url, None) {
+                let available = ui.content_region_avail();
+                let size = fit(texture.width as f32, texture.height as f32, available);
+
+                ui.image_config(texture.id(), size).build();
+                state.images.play(&key);
+            } else if state.images.is_failed(&key) {
+                ui.text_disabled("Failed to load image");
+            } else {
+                ui.text_disabled("Loading...");
+            }
+        });
+
+    if !opened {
+        state.viewed_attachment = None;
+    }
+}
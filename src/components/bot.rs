@@ -0,0 +1,102 @@
+use imgui::{Condition, TableFlags, Ui};
+use revolt_models::v0::Channel;
+
+use crate::{components::{avatar, icon}, names::{display_name, Author}, state::GlobalState};
+
+const ICON_SIZE: f32 = 16.0;
+
+/// Who the bot is and every server it's been added to, only available when logged in with a bot token.
+pub fn bot_overview(ui: &Ui, state: &mut GlobalState) {
+    if !state.bot_overview_open || !state.is_bot() {
+        return
+    };
+
+    let mut opened = true;
+
+    ui.window("Bot Overview")
+        .opened(&mut opened)
+        .size([600.0, 400.0], Condition::FirstUseEver)
+        .build(|| {
+            let bot = state.user_id.as_ref().and_then(|id| state.users.get(id)).cloned();
+
+            if let Some(bot) = &bot {
+                avatar(ui, state, bot, None, ICON_SIZE * 2.0);
+                ui.same_line();
+                ui.text(format!("{} ({})", bot.username, bot.id));
+
+                if let Some(info) = &bot.bot {
+                    let owner = display_name(state, None, Author::Id(&info.owner_id));
+
+                    ui.text_disabled(format!("Owned by {owner}"));
+                };
+            } else {
+                ui.text_disabled("Waiting for the gateway...");
+            };
+
+            ui.separator();
+            ui.text(format!("In {} servers", state.servers.len()));
+
+            let Some(_table) = ui.begin_table_with_flags("BotServers", 5, TableFlags::BORDERS_INNER_H | TableFlags::ROW_BG | TableFlags::SCROLL_Y) else {
+                return
+            };
+
+            ui.table_setup_column("Server");
+            ui.table_setup_column("Owner");
+            ui.table_setup_column("Channels");
+            ui.table_setup_column("Members");
+            ui.table_setup_column("");
+            ui.table_headers_row();
+
+            let mut servers = state.servers.values().cloned().collect::<Vec<_>>();
+            servers.sort_by_key(|server| server.name.to_lowercase());
+
+            for server in servers {
+                let _id = ui.push_id(&server.id);
+
+                ui.table_next_row();
+                ui.table_next_column();
+                icon(ui, state, server.icon.as_ref(), &server.name, ICON_SIZE);
+                ui.same_line();
+                ui.text(&server.name);
+
+                ui.table_next_column();
+                ui.text(display_name(state, Some(&server.id), Author::Id(&server.owner)));
+
+                ui.table_next_column();
+                ui.text(server.channels.len().to_string());
+
+                ui.table_next_column();
+                let loaded = state.members.get(&server.id).map_or(0, |members| members.len());
+
+                if state.full_member_lists.contains(&server.id) {
+                    ui.text(loaded.to_string());
+                } else {
+                    ui.text_disabled(format!("{loaded}+"));
+                    ui.same_line();
+
+                    if ui.small_button("Load") {
+                        state.load_all_members(&server.id);
+                    };
+                };
+
+                ui.table_next_column();
+
+                if ui.small_button("Open") {
+                    let first_text_channel = server.channels.iter()
+                        .find(|id| matches!(state.channels.get(*id), Some(Channel::TextChannel { .. })))
+                        .cloned();
+
+                    state.selected_server = Some(server.id.clone());
+                    state.selected_channel = first_text_channel;
+                };
+
+                ui.same_line();
+
+                if ui.small_button("Copy ID") {
+                    ui.set_clipboard_text(&server.id);
+                };
+            }
+        });
+
+    state.bot_overview_open = opened;
+}
@@ -1,37 +1,258 @@
-use imgui::Ui;
-use revolt_models::v0::{Channel, Message, Server};
+use imgui::{Key, MouseButton, StyleColor, Ui};
+use revolt_models::v0::{Channel, Member, Message, Server, User};
 
-use crate::state::GlobalState;
+use crate::{actions::Action, components::{attachment, avatar, connection_banner, embed, image_url, placeholder, rich_text}, fetch::FetchKey, markup::{content_spans, render_content}, names::{display_name, name_colour}, permissions::Permissions, state::{ChannelView, DisplayMode, GlobalState, MessageEdit, ScrollTarget}, time::{id_timestamp_ms, Clock}};
 
-pub fn message_view(ui: &Ui, state: &mut GlobalState, server: Option<&Server>, message: &Message) {
+const AVATAR_SIZE: f32 = 16.0;
+const COZY_AVATAR_SIZE: f32 = 32.0;
+/// Messages from the same author further apart than this start a new group.
+const GROUP_TIMEOUT_MS: i64 = 7 * 60 * 1000;
+const NEW_COLOUR: [f32; 4] = [0.93, 0.27, 0.27, 1.0];
+const QUICK_REACTIONS: &[&str] = &["👍", "❤️", "😂", "😮", "😢", "🎉"];
+
+fn new_messages_divider(ui: &Ui) {
+    let pos = ui.cursor_screen_pos();
+    let width = ui.content_region_avail()[0];
+    let height = ui.text_line_height();
+    let text_size = ui.calc_text_size("NEW");
+
+    let draw_list = ui.get_window_draw_list();
+
+    draw_list
+        .add_line([pos[0], pos[1] + height / 2.0], [pos[0] + width - text_size[0] - 8.0, pos[1] + height / 2.0], NEW_COLOUR)
+        .thickness(1.0)
+        .build();
+
+    draw_list.add_text([pos[0] + width - text_size[0], pos[1]], NEW_COLOUR, "NEW");
+
+    ui.dummy([width, height]);
+}
+
+/// Floating bar pinned to the top of the message list while scrolled away from the bottom.
+fn jump_bar(ui: &Ui, view: &mut ChannelView, show_first_unread: bool) {
+    ui.set_cursor_pos([ui.scroll_x() + 4.0, ui.scroll_y() + 4.0]);
+
+    let _bg = ui.push_style_color(StyleColor::ChildBg, ui.style_color(StyleColor::PopupBg));
+
+    ui.child_window("JumpBar")
+        .size([-4.0, ui.frame_height_with_spacing() + ui.clone_style().window_padding[1]])
+        .border(true)
+        .scroll_bar(false)
+        .build(|| {
+            if show_first_unread && ui.small_button("Jump to first unread") {
+                view.scroll_to = Some(ScrollTarget::FirstUnread);
+            };
+
+            if !view.at_bottom {
+                if show_first_unread {
+                    ui.same_line();
+                };
+
+                if ui.small_button("Jump to present") {
+                    view.scroll_to = Some(ScrollTarget::Present);
+                };
+            };
+        });
+}
+
+/// Whether `message` continues the group started by `previous` and can skip its header.
+fn is_grouped(clock: &Clock, previous: &Message, message: &Message) -> bool {
+    let same_masquerade = match (&previous.masquerade, &message.masquerade) {
+        (Some(a), Some(b)) => a.name == b.name && a.avatar == b.avatar,
+        (None, None) => true,
+        _ => false
+    };
+
+    let (previous_ms, message_ms) = (id_timestamp_ms(&previous.id), id_timestamp_ms(&message.id));
+
+    previous.author == message.author
+        && same_masquerade
+        && previous.system.is_none()
+        && message.replies.as_ref().map_or(true, |replies| replies.is_empty())
+        && clock.is_same_day(previous_ms, message_ms)
+        && (message_ms - previous_ms).abs() < GROUP_TIMEOUT_MS
+}
+
+fn day_separator(ui: &Ui, clock: &Clock, message: &Message) {
+    let label = clock.day(id_timestamp_ms(&message.id));
+    let pos = ui.cursor_screen_pos();
+    let width = ui.content_region_avail()[0];
+    let height = ui.text_line_height();
+    let text_size = ui.calc_text_size(&label);
+    let text_x = pos[0] + (width - text_size[0]) / 2.0;
+    let colour = ui.style_color(StyleColor::Separator);
+
+    let draw_list = ui.get_window_draw_list();
+
+    draw_list
+        .add_line([pos[0], pos[1] + height / 2.0], [text_x - 8.0, pos[1] + height / 2.0], colour)
+        .build();
+
+    draw_list
+        .add_line([text_x + text_size[0] + 8.0, pos[1] + height / 2.0], [pos[0] + width, pos[1] + height / 2.0], colour)
+        .build();
+
+    draw_list.add_text([text_x, pos[1]], ui.style_color(StyleColor::TextDisabled), &label);
+
+    ui.dummy([width, height]);
+}
+
+fn author_avatar(ui: &Ui, state: &mut GlobalState, message: &Message, user: Option<&User>, member: Option<&Member>, size: f32) {
+    match (message.masquerade.as_ref().and_then(|masq| masq.avatar.as_deref()), user) {
+        (Some(url), _) => {
+            let proxied = state.config.january_proxy_url(url);
+
+            image_url(ui, state, &format!("masquerade:{url}"), &proxied, [size, size], size / 2.0);
+        },
+        (None, Some(user)) => avatar(ui, state, user, member, size),
+        (None, None) => placeholder(ui, [size, size], size / 2.0)
+    };
+}
+
+/// Draws the message time, with the full date in a tooltip.
+fn timestamp(ui: &Ui, state: &GlobalState, message: &Message, text: &str, highlighted: bool) {
+    if highlighted {
+        ui.text(text);
+    } else {
+        ui.text_disabled(text);
+    }
+
+    if ui.is_item_hovered() {
+        ui.tooltip_text(state.clock.full(id_timestamp_ms(&message.id), state.settings.clock_format));
+    }
+}
+
+/// Replaces the message content while it's being edited, Enter saves and Escape cancels.
+fn edit_box(ui: &Ui, state: &mut GlobalState) {
+    let Some(edit) = state.editing.as_mut() else { return };
+
+    ui.set_next_item_width(-1.0);
+
+    let submitted = ui.input_text("##edit", &mut edit.content)
+        .enter_returns_true(true)
+        .build();
+
+    ui.text_disabled("Enter to save, Escape to cancel");
+
+    if submitted {
+        if let Some(edit) = state.editing.take() {
+            state.actions.dispatch(Action::EditMessage { channel: edit.channel_id, message: edit.message_id, content: edit.content });
+        }
+    } else if ui.is_key_pressed(Key::Escape) {
+        state.editing = None;
+    };
+}
+
+/// Reaction counts under a message, clicking one adds or removes our own reaction.
+fn reactions(ui: &Ui, state: &GlobalState, message: &Message) {
+    for (index, (emoji, users)) in message.reactions.iter().enumerate() {
+        if index > 0 {
+            ui.same_line();
+        };
+
+        let reacted = state.user_id.as_ref().is_some_and(|id| users.contains(id));
+        // Custom emoji are referenced by id and aren't loaded yet.
+        let label = if ulid::Ulid::from_string(emoji).is_ok() { ":emoji:" } else { emoji.as_str() };
+
+        let _colour = reacted.then(|| ui.push_style_color(StyleColor::Button, ui.style_color(StyleColor::ButtonActive)));
+
+        if ui.small_button(format!("{label} {}##{emoji}", users.len())) {
+            let (channel, message, emoji) = (message.channel.clone(), message.id.clone(), emoji.clone());
+
+            state.actions.dispatch(if reacted {
+                Action::Unreact { channel, message, emoji }
+            } else {
+                Action::React { channel, message, emoji }
+            });
+        };
+    }
+}
+
+fn message_menu(ui: &Ui, state: &mut GlobalState, message: &Message) {
+    let own = state.user_id.as_deref() == Some(message.author.as_str());
+    let permissions = state.permissions(&message.channel);
+
+    if permissions.has(Permissions::REACT) {
+        ui.menu("Add Reaction", || {
+            for emoji in QUICK_REACTIONS {
+                if ui.menu_item(emoji) {
+                    state.actions.dispatch(Action::React { channel: message.channel.clone(), message: message.id.clone(), emoji: emoji.to_string() });
+                }
+            }
+        });
+    };
+
+    if let Some(content) = &message.content {
+        if ui.menu_item("Copy Text") {
+            ui.set_clipboard_text(content);
+        };
+
+        if own && message.system.is_none() && ui.menu_item("Edit") {
+            state.editing = Some(MessageEdit {
+                channel_id: message.channel.clone(),
+                message_id: message.id.clone(),
+                content: content.clone(),
+            });
+        };
+    };
+
+    if (own || permissions.has(Permissions::MANAGE_MESSAGES)) && ui.menu_item("Delete") {
+        state.actions.dispatch(Action::DeleteMessage { channel: message.channel.clone(), message: message.id.clone() });
+    };
+}
+
+fn message_body(ui: &Ui, state: &mut GlobalState, server: Option<&str>, message: &Message) {
+    if state.editing.as_ref().is_some_and(|edit| edit.message_id == message.id) {
+        edit_box(ui, state);
+    } else if let Some(content) = &message.content {
+        rich_text(ui, &content_spans(state, server, content))
+    }
+
+    for file in message.attachments.as_deref().unwrap_or_default() {
+        attachment(ui, state, file);
+    }
+
+    for e in message.embeds.as_deref().unwrap_or_default() {
+        embed(ui, state, e);
+    }
+
+    reactions(ui, state, message);
+}
+
+pub fn message_view(ui: &Ui, state: &mut GlobalState, server: Option<&Server>, message: &Message, grouped: bool) {
     ui.group(|| {
-        let timestamp_ms = ulid::Ulid::from_string(&message.id).unwrap().timestamp_ms();
-        let timestamp = eos::Timestamp::from_milliseconds(timestamp_ms as i64);
-        let datetime = eos::DateTime::from_timestamp(timestamp, eos::Utc);
-        let created_at = eos::format_dt!("[%H:%M]", datetime).to_string();
+        let datetime = state.clock.from_id(&message.id);
+        let created_at = state.clock.time(&datetime, state.settings.clock_format);
+        let hovered = state.current_message_hover.as_deref().is_some_and(|v| v == &message.id);
+
+        let server_id = server.map(|s| s.id.as_str());
+        // Authors missing from the cache are fetched in the background, placeholders are drawn until then.
+        state.request_user(&message.author);
+
+        if let Some(server_id) = server_id {
+            state.request_member(server_id, &message.author);
+        };
 
-        let user = &state.users[&message.author];
-        let member = server.map(|s| &state.members[&s.id][&user.id]);
+        let user = state.users.get(&message.author).cloned();
+        let member = server_id
+            .and_then(|server_id| state.members.get(server_id))
+            .and_then(|members| members.get(&message.author))
+            .cloned();
 
         ui.indent();
 
         for reply in message.replies.clone().unwrap_or_default() {
-            if let Some(message) = state.messages[&message.channel].iter().find(|m| m.id == reply) {
-                let user = &state.users[&message.author];
-                let member = server.map(|s| &state.members[&s.id][&user.id]);
-
-                let name = message.masquerade
-                    .as_ref()
-                    .and_then(|masq| masq.name.as_deref())
-                    .or(member.and_then(|m| m.nickname.as_deref()))
-                    .or(user.display_name.as_deref())
-                    .unwrap_or(&user.username);
-
-                ui.text_disabled(name);
+            if let Some(reply) = state.messages.get(&message.channel).and_then(|messages| messages.iter().find(|m| m.id == reply)) {
+                let name = display_name(state, server_id, reply);
+
+                match name_colour(state, server_id, reply) {
+                    Some(colour) => ui.text_colored(colour, &name),
+                    None => ui.text_disabled(&name)
+                };
                 ui.same_line();
 
-                if let Some(content) = message.content.as_ref() {
-                    ui.text_disabled(content)
+                if let Some(content) = reply.content.as_ref() {
+                    ui.text_disabled(render_content(state, server_id, content))
                 }
 
             }
@@ -39,57 +260,208 @@ pub fn message_view(ui: &Ui, state: &mut GlobalState, server: Option<&Server>, m
 
         ui.unindent();
 
-        if state.current_message_hover.as_deref().is_some_and(|v| v == &message.id) {
-            ui.text(&created_at);
-        } else {
-            ui.text_disabled(&created_at);
-        }
-        ui.same_line();
+        let loading = user.is_none() && state.fetcher.is_loading(&FetchKey::User(message.author.clone()));
+        let name = if loading { "Loading...".to_string() } else { display_name(state, server_id, message) };
+        let colour = name_colour(state, server_id, message).unwrap_or(ui.style_color(StyleColor::Text));
+
+        match state.settings.display_mode {
+            DisplayMode::Compact => {
+                timestamp(ui, state, message, &format!("[{created_at}]"), hovered);
+                ui.same_line();
+
+                if !grouped {
+                    author_avatar(ui, state, message, user.as_ref(), member.as_ref(), AVATAR_SIZE);
+                    ui.same_line();
+
+                    ui.text_colored(colour, &name);
+                    ui.same_line();
+                }
+
+                message_body(ui, state, server_id, message);
+            },
+            DisplayMode::Cozy if grouped => {
+                let indent = COZY_AVATAR_SIZE + ui.clone_style().item_spacing[0];
 
-        let name = message.masquerade
-            .as_ref()
-            .and_then(|masq| masq.name.as_deref())
-            .or(member.and_then(|m| m.nickname.as_deref()))
-            .or(user.display_name.as_deref())
-            .unwrap_or(&user.username);
+                if hovered {
+                    let pos = ui.cursor_screen_pos();
 
-        ui.text_colored([0.8, 0.0, 0.0, 1.0], name);
-        ui.same_line();
+                    ui.get_window_draw_list()
+                        .add_text(pos, ui.style_color(StyleColor::TextDisabled), &created_at);
+                }
+
+                ui.indent_by(indent);
+                message_body(ui, state, server_id, message);
+                ui.unindent_by(indent);
+            },
+            DisplayMode::Cozy => {
+                author_avatar(ui, state, message, user.as_ref(), member.as_ref(), COZY_AVATAR_SIZE);
+                ui.same_line();
+
+                ui.group(|| {
+                    ui.text_colored(colour, &name);
+                    ui.same_line();
+
+                    let created_at = state.clock.relative(id_timestamp_ms(&message.id), state.settings.clock_format);
+                    timestamp(ui, state, message, &created_at, false);
 
-        if let Some(content) = &message.content {
-            ui.text_wrapped(content)
+                    message_body(ui, state, server_id, message);
+                });
+            }
         }
     });
 
+    let menu_id = format!("message:{}", message.id);
+
     if ui.is_item_hovered() {
-        state.current_message_hover = Some(message.id.clone())
-    }
+        state.current_message_hover = Some(message.id.clone());
+
+        if ui.is_mouse_clicked(MouseButton::Right) {
+            ui.open_popup(&menu_id);
+        }
+    };
+
+    ui.popup(&menu_id, || message_menu(ui, state, message));
 }
 
 pub fn channel(ui: &Ui, state: &mut GlobalState) {
-    if let Some(selected_channel) = state.selected_channel.as_deref() {
-        let channel = &state.channels[selected_channel];
+    connection_banner(ui, state);
+
+    if let Some(selected_channel) = state.selected_channel.clone() {
+        let selected_channel = selected_channel.as_str();
+        let Some(channel) = state.channels.get(selected_channel).cloned() else {
+            ui.text_disabled("Unknown channel");
+            return
+        };
+        let channel = &channel;
 
         let server = match channel {
             Channel::TextChannel { server, .. } | Channel::VoiceChannel { server, .. } => state.servers.get(server).cloned(),
             _ => None
         };
 
-        let channel_messages = &state.messages[selected_channel].clone();
+        if !state.history_errors.contains_key(selected_channel) {
+            state.load_latest_messages(selected_channel);
+        };
+
+        let fresh = state.history_fresh.contains(selected_channel);
+        let context = state.new_context(format!("History:{selected_channel}"));
+
+        // Only the first load moves to the first unread message, fetching again after a reconnect keeps our place.
+        let first_load = context.use_hook(|| true);
+
+        if fresh && *first_load {
+            *first_load = false;
 
-        let channel_name = match &channel {
-            Channel::SavedMessages { .. } => "Saved Messages",
-            Channel::DirectMessage { .. } => "DM TODO",
-            Channel::Group { name, .. } | Channel::TextChannel { name, .. } | Channel::VoiceChannel { name, .. } => name,
+            // Work out the first unread message again now the history is here.
+            state.channel_view.channel_id = None;
         };
 
+        let channel_messages = &state.messages.get(selected_channel).cloned().unwrap_or_default();
+
+        if state.channel_view.channel_id.as_deref() != Some(selected_channel) {
+            let first_unread = state.unread_summary(selected_channel).unread
+                .then(|| {
+                    let last_read = state.unreads.get(selected_channel).and_then(|r| r.last_id.as_ref());
+
+                    channel_messages.iter()
+                        .find(|m| last_read.map_or(true, |last_read| &m.id > last_read))
+                        .map(|m| m.id.clone())
+                })
+                .flatten();
+
+            state.channel_view = ChannelView {
+                channel_id: Some(selected_channel.to_string()),
+                scroll_to: Some(if first_unread.is_some() { ScrollTarget::FirstUnread } else { ScrollTarget::Present }),
+                first_unread,
+                at_bottom: true,
+            };
+        };
+
+        // The channel's latest message can be ahead of what's loaded when it was deleted or history is stale, only
+        // ack what's actually newer than the read state so this settles.
+        if let Some(last_message) = channel_messages.last() {
+            let read_up_to = state.unreads.get(selected_channel).and_then(|read_state| read_state.last_id.as_deref());
+
+            if state.unread_summary(selected_channel).unread && read_up_to.map_or(true, |last_id| last_message.id.as_str() > last_id) {
+                state.ack(selected_channel, &last_message.id);
+            }
+        };
+
+        let channel_name = state.channel_name(channel);
+
         ui.text_disabled(channel_name);
 
+        if let Some(error) = state.history_errors.get(selected_channel) {
+            ui.text_colored(NEW_COLOUR, format!("Failed to load messages: {error}"));
+            ui.same_line();
+
+            if ui.small_button("Retry") {
+                state.load_latest_messages(selected_channel);
+            };
+        } else if !fresh {
+            ui.text_disabled("Loading messages...");
+        };
+
         ui.child_window("Messages")
         .always_vertical_scrollbar(true)
         .build(|| {
+            // Measured before drawing so a message arriving this frame doesn't unstick us from the bottom.
+            let at_bottom = ui.scroll_y() >= ui.scroll_max_y() - 1.0;
+            let mut first_unread_visible = false;
+
+            if !channel_messages.is_empty() && !state.history_start.contains(selected_channel) {
+                let loading = state.history_loading.contains_key(selected_channel);
+
+                ui.disabled(loading, || {
+                    if ui.small_button(if loading { "Loading..." } else { "Load older messages" }) {
+                        state.load_older_messages(selected_channel);
+                    }
+                });
+            };
+
+            let mut previous: Option<&Message> = None;
+
             for message in channel_messages {
-                message_view(ui, state, server.as_ref(), &message)
+                let new_day = previous.map_or(true, |previous| !state.clock.is_same_day(id_timestamp_ms(&previous.id), id_timestamp_ms(&message.id)));
+
+                if new_day {
+                    day_separator(ui, &state.clock, message);
+                };
+
+                let is_first_unread = state.channel_view.first_unread.as_ref() == Some(&message.id);
+
+                if is_first_unread {
+                    new_messages_divider(ui);
+                    first_unread_visible = ui.is_item_visible();
+
+                    if matches!(state.channel_view.scroll_to, Some(ScrollTarget::FirstUnread)) {
+                        ui.set_scroll_here_y_with_ratio(0.25);
+                        state.channel_view.scroll_to = None;
+                    };
+                };
+
+                let grouped = !new_day && !is_first_unread && previous.is_some_and(|previous| is_grouped(&state.clock, previous, message));
+
+                message_view(ui, state, server.as_ref(), message, grouped);
+
+                previous = Some(message);
+            };
+
+            match state.channel_view.scroll_to {
+                Some(ScrollTarget::Present) => {
+                    ui.set_scroll_here_y_with_ratio(1.0);
+                    state.channel_view.scroll_to = None;
+                },
+                None if at_bottom => ui.set_scroll_here_y_with_ratio(1.0),
+                _ => {}
+            };
+
+            state.channel_view.at_bottom = at_bottom;
+
+            let show_first_unread = state.channel_view.first_unread.is_some() && !first_unread_visible;
+
+            if show_first_unread || !at_bottom {
+                jump_bar(ui, &mut state.channel_view, show_first_unread);
             };
         });
     } else {
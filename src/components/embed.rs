@@ -0,0 +1,130 @@
+use imgui::Ui;
+use revolt_models::v0::{Embed, Image, Metadata, Video};
+
+use crate::{colour::parse_colour, components::{image, image_url}, state::GlobalState};
+
+const EMBED_WIDTH: f32 = 400.0;
+const ICON_SIZE: f32 = 16.0;
+const LINK_COLOUR: [f32; 4] = [0.0, 0.66, 0.99, 1.0];
+const DEFAULT_COLOUR: [f32; 4] = [0.4, 0.4, 0.4, 1.0];
+
+fn fit(width: f32, height: f32, max: f32) -> [f32; 2] {
+    let scale = (max / width).min(max / height).min(1.0);
+
+    [width * scale, height * scale]
+}
+
+fn proxied_image(ui: &Ui, state: &mut GlobalState, url: &str, size: [f32; 2]) {
+    let proxied = state.config.january_proxy_url(url);
+
+    image_url(ui, state, &format!("embed:{url}"), &proxied, size, 0.0);
+}
+
+fn link(ui: &Ui, text: &str, url: Option<&str>) {
+    match url {
+        Some(url) => {
+            ui.text_colored(LINK_COLOUR, text);
+
+            if ui.is_item_hovered() {
+                ui.tooltip_text(format!("{url}\n(click to copy)"));
+            };
+
+            if ui.is_item_clicked() {
+                ui.set_clipboard_text(url);
+            };
+        },
+        None => ui.text(text)
+    }
+}
+
+fn image_embed(ui: &Ui, state: &mut GlobalState, image: &Image) {
+    let size = fit(image.width as f32, image.height as f32, EMBED_WIDTH);
+
+    proxied_image(ui, state, &image.url, size);
+}
+
+fn video_embed(ui: &Ui, video: &Video) {
+    let size = fit(video.width as f32, video.height as f32, EMBED_WIDTH);
+    let pos = ui.cursor_screen_pos();
+
+    ui.get_window_draw_list()
+        .add_rect(pos, [pos[0] + size[0], pos[1] + size[1]], [0.1, 0.1, 0.1, 1.0])
+        .filled(true)
+        .build();
+
+    ui.dummy(size);
+    link(ui, &format!("Video ({}x{})", video.width, video.height), Some(&video.url));
+}
+
+/// Draws the body of an embed with a coloured bar down its left side.
+fn framed(ui: &Ui, colour: Option<&str>, body: impl FnOnce()) {
+    ui.indent_by(8.0);
+    ui.group(body);
+    ui.unindent_by(8.0);
+
+    let (min, max) = (ui.item_rect_min(), ui.item_rect_max());
+    let colour = colour.and_then(parse_colour).unwrap_or(DEFAULT_COLOUR);
+
+    ui.get_window_draw_list()
+        .add_rect([min[0] - 8.0, min[1]], [min[0] - 4.0, max[1]], colour)
+        .filled(true)
+        .build();
+}
+
+pub fn embed(ui: &Ui, state: &mut GlobalState, embed: &Embed) {
+    match embed {
+        Embed::Website(website) => framed(ui, website.colour.as_deref(), || {
+            if let Some(site_name) = &website.site_name {
+                if let Some(icon_url) = &website.icon_url {
+                    proxied_image(ui, state, icon_url, [ICON_SIZE, ICON_SIZE]);
+                    ui.same_line();
+                };
+
+                ui.text_disabled(site_name);
+            };
+
+            if let Some(title) = &website.title {
+                link(ui, title, website.url.as_deref());
+            };
+
+            if let Some(description) = &website.description {
+                ui.text_wrapped(description);
+            };
+
+            if let Some(image) = &website.image {
+                image_embed(ui, state, image);
+            } else if let Some(video) = &website.video {
+                video_embed(ui, video);
+            };
+        }),
+        Embed::Text(text) => framed(ui, text.colour.as_deref(), || {
+            if let Some(icon_url) = &text.icon_url {
+                proxied_image(ui, state, icon_url, [ICON_SIZE, ICON_SIZE]);
+
+                if text.title.is_some() {
+                    ui.same_line();
+                }
+            };
+
+            if let Some(title) = &text.title {
+                link(ui, title, text.url.as_deref());
+            };
+
+            if let Some(description) = &text.description {
+                ui.text_wrapped(description);
+            };
+
+            if let Some(media) = &text.media {
+                let size = match &media.metadata {
+                    Metadata::Image { width, height } => fit(*width as f32, *height as f32, EMBED_WIDTH),
+                    _ => [EMBED_WIDTH, EMBED_WIDTH / 2.0]
+                };
+
+                image(ui, state, media, size);
+            };
+        }),
+        Embed::Image(image) => image_embed(ui, state, image),
+        Embed::Video(video) => video_embed(ui, video),
+        Embed::None => {}
+    }
+}
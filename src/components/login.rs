@@ -0,0 +1,340 @@
+use imgui::{ComboBoxFlags, Condition, Ui, WindowFlags};
+
+use crate::{app::{AuthPage, LoginOutcome, LoginScreen, MfaChallenge}, components::{account_page, captcha_field, poll_account_request}, auth::{AuthClient, Credentials, LoginRequest, LoginResponse, MfaMethod, MfaResponse}, http::RevoltConfig, instance::DEFAULT_INSTANCE, state::Request};
+
+const ERROR_COLOUR: [f32; 4] = [0.9, 0.3, 0.3, 1.0];
+const NOTICE_COLOUR: [f32; 4] = [0.3, 0.8, 0.4, 1.0];
+
+fn method_name(method: MfaMethod) -> &'static str {
+    match method {
+        MfaMethod::Totp => "Authenticator App",
+        MfaMethod::Recovery => "Recovery Code",
+        MfaMethod::Password => "Password",
+    }
+}
+
+/// Window in the middle of the main viewport with the given contents, shared by the screens shown before login.
+pub fn centered_window(ui: &Ui, title: &str, contents: impl FnOnce()) {
+    let viewport = ui.main_viewport();
+    let centre = [
+        viewport.work_pos[0] + viewport.work_size[0] / 2.0,
+        viewport.work_pos[1] + viewport.work_size[1] / 2.0,
+    ];
+
+    ui.window(title)
+        .position(centre, Condition::Always)
+        .position_pivot([0.5, 0.5])
+        .flags(WindowFlags::ALWAYS_AUTO_RESIZE | WindowFlags::NO_COLLAPSE | WindowFlags::NO_MOVE | WindowFlags::NO_DOCKING | WindowFlags::NO_SAVED_SETTINGS)
+        .build(contents);
+}
+
+/// Shown while the saved accounts are being signed back in.
+pub fn resuming(ui: &Ui) {
+    centered_window(ui, "Signing In###Login", || {
+        ui.text_disabled("Connecting to your saved accounts...");
+    });
+}
+
+fn submit(screen: &mut LoginScreen, auth: &AuthClient, request: LoginRequest) {
+    let auth = auth.clone();
+
+    screen.error = None;
+    screen.notice = None;
+    screen.request = Some(Request::spawn(async move { auth.login(request).await }));
+}
+
+fn page_title(page: AuthPage) -> &'static str {
+    match page {
+        AuthPage::Login => "Log In",
+        AuthPage::Register => "Create Account",
+        AuthPage::Verify => "Verify Email",
+        AuthPage::ResendVerification => "Resend Verification",
+        AuthPage::RequestReset => "Forgot Password",
+        AuthPage::ConfirmReset => "Reset Password",
+        AuthPage::BotToken => "Log In as Bot",
+    }
+}
+
+fn credentials_form(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient, config: &RevoltConfig) {
+    ui.input_text("Email", &mut screen.email).build();
+
+    let entered = ui.input_text("Password", &mut screen.password)
+        .password(true)
+        .enter_returns_true(true)
+        .build();
+
+    ui.input_text("Session Name", &mut screen.session_name)
+        .hint("Shown in your list of sessions")
+        .build();
+
+    let captcha = captcha_field(ui, screen, config);
+
+    ui.checkbox("Stay logged in", &mut screen.remember);
+
+    let ready = !screen.email.is_empty() && !screen.password.is_empty() && captcha.is_some();
+
+    ui.disabled(!ready, || {
+        if ui.button("Log In") || (entered && ready) {
+            let request = LoginRequest::Email {
+                email: screen.email.trim().to_string(),
+                password: screen.password.clone(),
+                captcha: captcha.flatten(),
+                friendly_name: screen.session_name.clone(),
+            };
+
+            submit(screen, auth, request);
+        };
+    });
+
+    ui.separator();
+
+    if ui.button("Create Account") {
+        screen.page = AuthPage::Register;
+    };
+
+    ui.same_line();
+
+    if ui.button("Use Bot Token") {
+        screen.page = AuthPage::BotToken;
+    };
+
+    // Verification and password resets both happen over email.
+    if config.features.email {
+        ui.same_line();
+
+        if ui.button("Forgot Password") {
+            screen.page = AuthPage::RequestReset;
+        };
+
+        ui.same_line();
+
+        if ui.button("Verify Email") {
+            screen.page = AuthPage::Verify;
+        };
+    };
+}
+
+pub fn bot_token_form(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient) {
+    ui.text_wrapped("Bots can read and moderate the servers they're in, but have no friends, unreads or settings.");
+
+    let entered = ui.input_text("Bot Token", &mut screen.bot_token)
+        .password(true)
+        .enter_returns_true(true)
+        .build();
+
+    ui.checkbox("Stay logged in", &mut screen.remember);
+
+    let token = screen.bot_token.trim().to_string();
+
+    ui.disabled(token.is_empty(), || {
+        if ui.button("Log In") || (entered && !token.is_empty()) {
+            let auth = auth.clone();
+
+            screen.error = None;
+            screen.bot_check = Some(Request::spawn(async move { auth.check_bot_token(&token).await }));
+        };
+    });
+}
+
+fn mfa_form(ui: &Ui, screen: &mut LoginScreen, auth: &AuthClient) {
+    let Some(mfa) = screen.mfa.as_mut() else { return };
+
+    ui.text("Two-factor authentication is enabled on this account.");
+
+    for method in mfa.methods.iter().copied().filter(|method| *method != MfaMethod::Password) {
+        ui.radio_button(method_name(method), &mut mfa.method, method);
+    }
+
+    let hint = match mfa.method {
+        MfaMethod::Recovery => "xxxx-xxxx",
+        _ => "123456",
+    };
+
+    let entered = ui.input_text("Code", &mut mfa.code)
+        .hint(hint)
+        .enter_returns_true(true)
+        .build();
+
+    let code = mfa.code.trim().to_string();
+    let mut verify = false;
+
+    ui.disabled(code.is_empty(), || {
+        verify = ui.button("Verify") || (entered && !code.is_empty());
+    });
+
+    if verify {
+        let mfa_response = match mfa.method {
+            MfaMethod::Recovery => MfaResponse::Recovery { recovery_code: code },
+            _ => MfaResponse::Totp { totp_code: code },
+        };
+
+        let request = LoginRequest::Mfa {
+            mfa_ticket: mfa.ticket.clone(),
+            mfa_response,
+            friendly_name: screen.session_name.clone(),
+        };
+
+        submit(screen, auth, request);
+    };
+
+    ui.same_line();
+
+    if ui.button("Back") {
+        screen.mfa = None;
+        screen.error = None;
+    };
+}
+
+/// The instance field with its recent instances, forms are only shown once the instance's configuration checks out.
+fn instance_picker(ui: &Ui, screen: &mut LoginScreen, busy: bool) {
+    if let Some(result) = screen.connecting.as_ref().and_then(Request::poll) {
+        screen.connecting = None;
+
+        match result {
+            Ok(instance) => {
+                screen.instance_url = instance.base_url.clone();
+                screen.instance = Some(instance);
+            },
+            Err(e) => screen.instance_error = Some(e)
+        }
+    };
+
+    let mut connect = false;
+
+    ui.disabled(busy || screen.connecting.is_some(), || {
+        connect = ui.input_text("Instance", &mut screen.instance_url)
+            .hint(DEFAULT_INSTANCE)
+            .enter_returns_true(true)
+            .build();
+
+        if !screen.recent_instances.is_empty() {
+            ui.same_line();
+
+            if let Some(_combo) = ui.begin_combo_with_flags("##RecentInstances", "", ComboBoxFlags::NO_PREVIEW) {
+                for recent in &screen.recent_instances {
+                    if ui.selectable(recent) {
+                        screen.instance_url = recent.clone();
+                        connect = true;
+                    };
+                }
+            };
+
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Recent instances");
+            };
+        };
+
+        let current = screen.current_instance().is_some();
+
+        ui.same_line();
+
+        if ui.button("Connect") && !current {
+            connect = true;
+        };
+    });
+
+    if connect {
+        screen.connect();
+    };
+
+    if screen.connecting.is_some() {
+        ui.text_disabled("Connecting...");
+    } else if let Some(error) = &screen.instance_error {
+        ui.text_colored(ERROR_COLOUR, error);
+    } else if let Some(instance) = &screen.instance {
+        ui.text_disabled(format!("Revolt {} at {}", instance.config.build.semver, instance.base_url));
+    };
+}
+
+/// Email and password login followed by the MFA challenge if the account has one, returns the new session and
+/// whether it should be remembered once the server accepts it. Also hosts bot login, the account creation and
+/// recovery forms and picking which instance to use. `can_cancel` is set when adding another account.
+pub fn login(ui: &Ui, screen: &mut LoginScreen, can_cancel: bool) -> Option<LoginOutcome> {
+    let mut outcome = None;
+    let mut credentials = None;
+
+    if let Some(result) = screen.request.as_ref().and_then(Request::poll) {
+        screen.request = None;
+
+        match result {
+            Ok(LoginResponse::Success(new_session)) => {
+                screen.password.clear();
+                credentials = Some(Credentials::Session(new_session));
+            },
+            Ok(LoginResponse::Mfa { ticket, allowed_methods }) => {
+                let method = if allowed_methods.contains(&MfaMethod::Totp) { MfaMethod::Totp } else { MfaMethod::Recovery };
+
+                screen.mfa = Some(MfaChallenge { ticket, methods: allowed_methods, method, code: String::new() });
+            },
+            Ok(LoginResponse::Disabled { .. }) => screen.error = Some("This account has been disabled".to_string()),
+            Err(e) => screen.error = Some(e)
+        }
+    };
+
+    if let Some(result) = screen.bot_check.as_ref().and_then(Request::poll) {
+        screen.bot_check = None;
+
+        match result {
+            Ok(()) => credentials = Some(Credentials::Bot { bot_token: std::mem::take(&mut screen.bot_token).trim().to_string() }),
+            Err(e) => screen.error = Some(e)
+        }
+    };
+
+    if let (Some(credentials), Some(instance)) = (credentials, screen.instance.clone()) {
+        outcome = Some(LoginOutcome::LoggedIn(instance, credentials, screen.remember));
+    };
+
+    poll_account_request(screen);
+
+    // The title is also the window's id, keep it fixed so switching pages doesn't move it.
+    centered_window(ui, &format!("{}###Login", page_title(screen.page)), || {
+        let busy = screen.request.is_some() || screen.pending.is_some() || screen.bot_check.is_some();
+
+        instance_picker(ui, screen, busy);
+        ui.separator();
+
+        if let Some(instance) = screen.current_instance().cloned() {
+            let (auth, config) = (&instance.auth, &instance.config);
+
+            if let Some(notice) = screen.notice {
+                ui.text_colored(NOTICE_COLOUR, notice);
+            };
+
+            ui.disabled(busy, || {
+                match screen.page {
+                    AuthPage::Login if screen.mfa.is_some() => mfa_form(ui, screen, auth),
+                    AuthPage::Login => credentials_form(ui, screen, auth, config),
+                    _ => {
+                        account_page(ui, screen, auth, config);
+
+                        ui.separator();
+
+                        if ui.button("Back to Log In") {
+                            screen.page = AuthPage::Login;
+                            screen.error = None;
+                            screen.notice = None;
+                        };
+                    }
+                };
+            });
+
+            if busy {
+                ui.text_disabled("Please wait...");
+            };
+
+            if let Some(error) = &screen.error {
+                ui.text_colored(ERROR_COLOUR, error);
+            };
+        };
+
+        if can_cancel {
+            ui.separator();
+
+            if ui.button("Cancel") {
+                outcome = Some(LoginOutcome::Cancelled);
+            };
+        };
+    });
+
+    outcome
+}
@@ -0,0 +1,103 @@
+use imgui::Ui;
+use revolt_models::v0::{File, Member, User};
+
+use crate::state::GlobalState;
+
+const PLACEHOLDER_COLOUR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+
+/// Rounds a requested size up so the same file at similar sizes shares one texture.
+fn size_bucket(size: [f32; 2]) -> u32 {
+    (size[0].max(size[1]).ceil() as u32).clamp(16, 2048).next_power_of_two()
+}
+
+pub fn placeholder(ui: &Ui, size: [f32; 2], rounding: f32) {
+    let pos = ui.cursor_screen_pos();
+
+    ui.get_window_draw_list()
+        .add_rect(pos, [pos[0] + size[0], pos[1] + size[1]], PLACEHOLDER_COLOUR)
+        .filled(true)
+        .rounding(rounding)
+        .build();
+
+    ui.dummy(size);
+}
+
+/// Draws the image at `url`, or a placeholder while it is loading. Returns whether the image was drawn.
+pub fn image_url(ui: &Ui, state: &mut GlobalState, key: &str, url: &str, size: [f32; 2], rounding: f32) -> bool {
+    let bucket = size_bucket(size);
+
+    let key = format!("{key}:{bucket}");
+    let animate_on_hover_only = state.settings.animate_on_hover_only;
+
+    match state.images.get(&key, url, Some(bucket)) {
+        Some(texture) => {
+            let pos = ui.cursor_screen_pos();
+            let hovered = ui.is_mouse_hovering_rect(pos, [pos[0] + size[0], pos[1] + size[1]]);
+            let animate = texture.is_animated() && (hovered || !animate_on_hover_only);
+            let id = if animate { texture.id() } else { texture.first_id() };
+
+            ui.get_window_draw_list()
+                .add_image_rounded(id, pos, [pos[0] + size[0], pos[1] + size[1]], rounding)
+                .build();
+
+            ui.dummy(size);
+
+            if animate && ui.is_item_visible() {
+                state.images.play(&key);
+            };
+
+            true
+        },
+        None => {
+            placeholder(ui, size, rounding);
+
+            false
+        }
+    }
+}
+
+/// Draws an autumn file at the given size.
+pub fn image(ui: &Ui, state: &mut GlobalState, file: &File, size: [f32; 2]) -> bool {
+    let url = state.config.autumn_url_sized(file, size_bucket(size));
+
+    image_url(ui, state, &file.id, &url, size, 0.0)
+}
+
+/// Draws a round avatar, preferring the member's server avatar over the user's own.
+pub fn avatar(ui: &Ui, state: &mut GlobalState, user: &User, member: Option<&Member>, size: f32) {
+    let file = member.and_then(|m| m.avatar.as_ref()).or(user.avatar.as_ref());
+
+    let (key, url) = match file {
+        Some(file) => (file.id.clone(), state.config.autumn_url_sized(file, size_bucket([size, size]))),
+        None => (format!("default_avatar:{}", user.id), format!("{}/users/{}/default_avatar", state.api_base(), user.id))
+    };
+
+    image_url(ui, state, &key, &url, [size, size], size / 2.0);
+}
+
+/// Draws a round server or group icon, falling back to the first letter of its name.
+pub fn icon(ui: &Ui, state: &mut GlobalState, file: Option<&File>, name: &str, size: f32) {
+    if let Some(file) = file {
+        let url = state.config.autumn_url_sized(file, size_bucket([size, size]));
+
+        image_url(ui, state, &file.id, &url, [size, size], size / 2.0);
+    } else {
+        let pos = ui.cursor_screen_pos();
+        let letter = name.chars().next().map(|c| c.to_string()).unwrap_or_default();
+        let text_size = ui.calc_text_size(&letter);
+        let draw_list = ui.get_window_draw_list();
+
+        draw_list
+            .add_circle([pos[0] + size / 2.0, pos[1] + size / 2.0], size / 2.0, PLACEHOLDER_COLOUR)
+            .filled(true)
+            .build();
+
+        draw_list.add_text(
+            [pos[0] + (size - text_size[0]) / 2.0, pos[1] + (size - text_size[1]) / 2.0],
+            [1.0, 1.0, 1.0, 1.0],
+            &letter,
+        );
+
+        ui.dummy([size, size]);
+    }
+}
@@ -1,70 +1,268 @@
 
-use imgui::{StyleColor, Ui};
-use revolt_models::v0::{Presence, UserStatus};
+use std::collections::BTreeMap;
 
-use crate::state::GlobalState;
+use imgui::{ItemHoveredFlags, ListClipper, StyleColor, Ui};
+use revolt_models::v0::{Presence, User};
 
+use crate::{actions::Action, colour::parse_css_colour, components::{avatar, image, placeholder, required_permission, rich_text}, fetch::FetchKey, http::EditMember, markup::content_spans, names::{display_name, name_colour, Author}, permissions::Permissions, state::{GlobalState, MemberRow, ModerationDialog, ModerationKind, RoleEditor}};
 
+const AVATAR_SIZE: f32 = 20.0;
+const PROFILE_AVATAR_SIZE: f32 = 64.0;
+
+fn is_online(user: &User) -> bool {
+    user.online && !matches!(user.status.as_ref().and_then(|status| status.presence.as_ref()), Some(Presence::Invisible))
+}
+
+fn presence_colour(user: &User) -> [f32; 4] {
+    if !is_online(user) {
+        return [0.45, 0.45, 0.45, 1.0]
+    };
+
+    match user.status.as_ref().and_then(|status| status.presence.as_ref()) {
+        Some(Presence::Idle) => [0.96, 0.74, 0.18, 1.0],
+        Some(Presence::Busy) => [0.93, 0.27, 0.27, 1.0],
+        Some(Presence::Focus) => [0.25, 0.55, 0.95, 1.0],
+        _ => [0.24, 0.75, 0.4, 1.0]
+    }
+}
+
+/// Groups members like the official client: online members under their highest hoisted role, then the rest of
+/// Online, then Offline, each sorted by name.
+fn build_rows(state: &GlobalState, server_id: &str, search: &str) -> Vec<MemberRow> {
+    let (Some(server), Some(members)) = (state.servers.get(server_id), state.members.get(server_id)) else {
+        return Vec::new()
+    };
+
+    let mut groups = BTreeMap::<(u8, i64), (String, Vec<(String, &str)>)>::new();
+
+    for (user_id, member) in members {
+        let user = state.users.get(user_id);
+        let name = display_name(state, Some(server_id), Author::Id(user_id));
+
+        if !search.is_empty() {
+            let matches = name.to_lowercase().contains(search)
+                || user.is_some_and(|user| user.username.to_lowercase().contains(search));
+
+            if !matches {
+                continue
+            }
+        };
+
+        let group = if user.is_some_and(is_online) {
+            member.roles.iter()
+                .filter_map(|id| server.roles.get(id))
+                .filter(|role| role.hoist)
+                .min_by_key(|role| role.rank)
+                .map(|role| ((0, role.rank), role.name.clone()))
+                .unwrap_or_else(|| ((1, 0), "Online".to_string()))
+        } else {
+            ((2, 0), "Offline".to_string())
+        };
+
+        groups.entry(group.0)
+            .or_insert_with(|| (group.1, Vec::new()))
+            .1
+            .push((name.to_lowercase(), user_id.as_str()));
+    }
+
+    let mut rows = Vec::new();
+
+    for (_, (name, mut members)) in groups {
+        members.sort();
+
+        rows.push(MemberRow::Header { name, count: members.len() });
+        rows.extend(members.into_iter().map(|(_, user_id)| MemberRow::Member(user_id.to_string())));
+    }
+
+    rows
+}
+
+fn member_row(ui: &Ui, state: &mut GlobalState, server_id: &str, user_id: &str) {
+    let name = display_name(state, Some(server_id), Author::Id(user_id));
+    let member = state.members.get(server_id).and_then(|members| members.get(user_id)).cloned();
+
+    match state.users.get(user_id).cloned() {
+        Some(user) => {
+            let position = ui.cursor_screen_pos();
+
+            avatar(ui, state, &user, member.as_ref(), AVATAR_SIZE);
+
+            let radius = AVATAR_SIZE * 0.2;
+            let centre = [position[0] + AVATAR_SIZE - radius, position[1] + AVATAR_SIZE - radius];
+            let draw_list = ui.get_window_draw_list();
+
+            draw_list.add_circle(centre, radius + 1.5, ui.style_color(StyleColor::WindowBg)).filled(true).build();
+            draw_list.add_circle(centre, radius, presence_colour(&user)).filled(true).build();
+        },
+        None => {
+            state.request_user(user_id);
+            placeholder(ui, [AVATAR_SIZE, AVATAR_SIZE], AVATAR_SIZE / 2.0);
+        }
+    };
+
+    ui.same_line();
+
+    let colour = name_colour(state, Some(server_id), Author::Id(user_id));
+    let colour_token = colour.map(|colour| ui.push_style_color(StyleColor::Text, colour));
+    let clicked = ui.selectable(format!("{name}##{user_id}"));
+
+    drop(colour_token);
+
+    if clicked {
+        state.selected_member = Some(user_id.to_string());
+
+        ui.open_popup("member")
+    };
+
+    if let Some(_t) = ui.begin_popup_context_item() {
+        moderation_items(ui, state, server_id, user_id);
+
+        _t.end()
+    };
+}
+
+/// Only shows actions we have the permission for, disabled with the reason when the member outranks us.
+fn moderation_items(ui: &Ui, state: &mut GlobalState, server_id: &str, user_id: &str) {
+    let permissions = state.server_permissions(server_id);
+
+    for (kind, label) in [(ModerationKind::Ban, "Ban"), (ModerationKind::Kick, "Kick"), (ModerationKind::Timeout, "Timeout")] {
+        if !permissions.has(required_permission(kind)) {
+            continue
+        };
+
+        let allowed = state.can_moderate(server_id, user_id, required_permission(kind));
+
+        if ui.menu_item_config(label).enabled(allowed.is_ok()).build() {
+            state.moderation = Some(ModerationDialog::new(kind, server_id.to_string(), user_id.to_string()));
+        };
+
+        if let Err(reason) = allowed {
+            if ui.is_item_hovered_with_flags(ItemHoveredFlags::ALLOW_WHEN_DISABLED) {
+                ui.tooltip_text(reason);
+            }
+        };
+    }
+
+    if permissions.has(Permissions::MANAGE_ROLE) && ui.menu_item("Manage Roles") {
+        state.role_editor = Some(RoleEditor::new(server_id.to_string()));
+    };
+}
 
 pub fn members(ui: &Ui, state: &mut GlobalState) {
     ui.child_window("Members")
     .horizontal_scrollbar(false)
     .build(|| {
-        if let Some(selected_server) = &state.selected_server {
-            let members: &std::collections::HashMap<String, revolt_models::v0::Member> = &state.members[selected_server];
+        if let Some(selected_server) = state.selected_server.clone() {
+            let selected_server = &selected_server;
 
-            ui.text_disabled(format!("{} Members", members.len()));
+            if let Some(banner) = state.servers.get(selected_server).and_then(|s| s.banner.clone()) {
+                let width = ui.content_region_avail()[0];
 
-            for member in members.values() {
-                let user = &state.users[&member.id.user];
+                image(ui, state, &banner, [width, width * 9.0 / 16.0]);
+            };
 
-                if ui.selectable(member.nickname.as_ref().or(user.display_name.as_ref()).unwrap_or(&user.username)) {
-                    state.selected_member = Some(member.id.user.clone());
+            let permissions = state.server_permissions(selected_server);
+            let member_count = state.members.get(selected_server).map(|members| members.len()).unwrap_or_default();
 
-                    ui.open_popup("member")
-                }
+            ui.text_disabled(format!("{member_count} Members"));
 
-                if let Some(_t) = ui.begin_popup_context_item() {
-                    if ui.menu_item("Ban") {}
-                    if ui.menu_item("Kick") {}
-                    if ui.menu_item("Timeout") {}
-                    if ui.menu_item("Manage Roles") {}
+            if !state.full_member_lists.contains(selected_server) {
+                let loading = state.fetcher.is_loading(&FetchKey::AllMembers(selected_server.clone()));
 
-                    _t.end()
-                }
+                ui.same_line();
+
+                ui.disabled(loading, || {
+                    if ui.small_button(if loading { "Loading..." } else { "Load All" }) {
+                        state.load_all_members(selected_server);
+                    }
+                });
             };
 
+            ui.set_next_item_width(-1.0);
+            ui.input_text("##search", &mut state.member_list.search)
+                .hint("Search members")
+                .build();
+
+            let key = (selected_server.clone(), state.member_list.search.to_lowercase(), state.members_version);
+
+            if state.member_list.built_from.as_ref() != Some(&key) {
+                state.member_list.rows = build_rows(state, selected_server, &key.1);
+                state.member_list.built_from = Some(key);
+            };
+
+            let rows = std::mem::take(&mut state.member_list.rows);
+            let row_height = AVATAR_SIZE + ui.clone_style().item_spacing[1];
+
+            let clipper = ListClipper::new(rows.len() as i32)
+                .items_height(row_height)
+                .begin(ui);
+
+            for index in clipper.iter() {
+                match &rows[index as usize] {
+                    MemberRow::Header { name, count } => {
+                        ui.dummy([0.0, AVATAR_SIZE]);
+                        ui.same_line();
+                        ui.text_disabled(format!("{name} - {count}"));
+                    },
+                    MemberRow::Member(user_id) => member_row(ui, state, selected_server, user_id)
+                }
+            }
+
+            state.member_list.rows = rows;
+
             ui.modal_popup_config("member")
             .save_settings(true)
             .always_auto_resize(false)
             .menu_bar(true)
             .collapsible(true)
             .build(|| {
-                if let Some(member_id) = &state.selected_member {
-                    let user = &state.users[member_id];
-                    let member = &state.members[selected_server][member_id];
+                if let Some(member_id) = state.selected_member.clone() {
+                    let user = state.users.get(&member_id).cloned();
+                    let member = state.members.get(selected_server).and_then(|members| members.get(&member_id)).cloned();
+
+                    let (Some(user), Some(member)) = (user, member) else {
+                        state.request_user(&member_id);
+                        state.request_member(selected_server, &member_id);
+
+                        ui.text_disabled("Loading...");
+
+                        if ui.button("Close") {
+                            ui.close_current_popup()
+                        };
+
+                        return
+                    };
+
+                    let bot = state.is_bot();
 
                     ui.menu_bar(|| {
                         ui.menu("User", || {
-                            if ui.menu_item("Send Friend Request") {}
-                            if ui.menu_item("Block") {}
+                            if !bot {
+                                if ui.menu_item("Send Friend Request") {}
+                                if ui.menu_item("Block") {}
+                            };
+
                             if ui.menu_item("Send Message") {}
                             if ui.menu_item("Copy ID") {}
                         });
 
-                        ui.menu("Moderation", || {
-                            if ui.menu_item("Ban") {}
-                            if ui.menu_item("Kick") {}
-                            if ui.menu_item("Timeout") {}
-                            if ui.menu_item("Manage Roles") {}
-                        })
+                        let moderation = [Permissions::BAN_MEMBERS, Permissions::KICK_MEMBERS, Permissions::TIMEOUT_MEMBERS, Permissions::MANAGE_ROLE];
+
+                        if moderation.iter().any(|p| permissions.has(*p)) {
+                            ui.menu("Moderation", || moderation_items(ui, state, selected_server, &member_id))
+                        }
                     });
 
                     if let Some(_tabbar_token) = ui.tab_bar("member_tabbar") {
                         if let Some(_tabitem_token) = ui.tab_item("User") {
-                            if let Some(display_name) = &user.display_name {
-                                ui.text(display_name);
-                            }
+                            avatar(ui, state, &user, Some(&member), PROFILE_AVATAR_SIZE);
+
+                            let name = display_name(state, Some(selected_server), &user);
+
+                            match name_colour(state, Some(selected_server), &user) {
+                                Some(colour) => ui.text_colored(colour, &name),
+                                None => ui.text(&name)
+                            };
 
                             ui.text_disabled(&user.username);
                             ui.same_line_with_spacing(0.0, 0.0);
@@ -90,19 +288,84 @@ pub fn members(ui: &Ui, state: &mut GlobalState) {
 
                             ui.new_line();
 
+                            let context = state.new_context("MemberProfile");
+                            let mut selected = false;
+
+                            // Fetched again whenever a member is picked, so profiles don't stay stale for long.
+                            context.use_effect(member_id.clone(), |_| selected = true);
+
+                            if selected {
+                                state.request_profile(&member_id);
+                            };
+
+                            let key = FetchKey::Profile(member_id.clone());
+
+                            match state.profiles.get(&member_id).cloned() {
+                                Some(profile) => {
+                                    if let Some(content) = profile.content.filter(|content| !content.is_empty()) {
+                                        ui.separator();
+                                        rich_text(ui, &content_spans(state, Some(selected_server), &content));
+                                    }
+                                },
+                                None if state.fetcher.is_loading(&key) => ui.text_disabled("Loading profile..."),
+                                None if state.fetcher.has_failed(&key) => ui.text_disabled("Couldn't load profile"),
+                                None => {}
+                            };
+
                         };
 
                         if let Some(_tabitem_token) = ui.tab_item("Roles") {
                             ui.child_window("Roles")
                                 .build(|| {
-                                    for (color, role) in &[([1.0, 0.0, 0.0, 1.0], "Admin"), ([0.0, 1.0, 0.0, 1.0], "Moderator"), ([0.0, 0.0, 1.0, 1.0], "User")] {
-                                        let color_token = ui.push_style_color(StyleColor::Text, *color);
+                                    let Some(server) = state.servers.get(selected_server).cloned() else { return };
 
-                                        if ui.button(role) {}
+                                    let can_assign = state.server_permissions(selected_server).has(Permissions::ASSIGN_ROLES);
+                                    let own_rank = state.user_id.as_deref()
+                                        .map(|id| state.member_rank(selected_server, id))
+                                        .unwrap_or(i64::MAX);
 
-                                        color_token.end();
+                                    let mut roles = server.roles.iter().collect::<Vec<_>>();
+                                    roles.sort_by_key(|(_, role)| role.rank);
+
+                                    for (role_id, role) in roles {
+                                        let mut has_role = member.roles.contains(role_id);
+
+                                        if !can_assign && !has_role {
+                                            continue
+                                        };
+
+                                        let colour = role.colour.as_deref().and_then(parse_css_colour);
+                                        let color_token = colour.map(|colour| ui.push_style_color(StyleColor::Text, colour));
+
+                                        if can_assign {
+                                            // Roles at or above our own can't be handed out.
+                                            let _disabled = ui.begin_disabled(role.rank <= own_rank);
+
+                                            if ui.checkbox(format!("{}##{role_id}", role.name), &mut has_role) {
+                                                let mut member_roles = member.roles.clone();
+
+                                                if has_role {
+                                                    member_roles.push(role_id.clone());
+                                                } else {
+                                                    member_roles.retain(|id| id != role_id);
+                                                };
+
+                                                state.actions.dispatch(Action::EditMember {
+                                                    server: selected_server.clone(),
+                                                    user: member_id.clone(),
+                                                    body: EditMember { roles: Some(member_roles), ..Default::default() },
+                                                });
+                                            };
+                                        } else {
+                                            ui.bullet_text(&role.name);
+                                        };
+
+                                        drop(color_token);
                                     };
 
+                                    if can_assign && server.roles.is_empty() {
+                                        ui.text_disabled("This server has no roles");
+                                    };
                                 });
 
                         };
@@ -1,12 +1,35 @@
 use imgui::Ui;
 use revolt_models::v0::DataMessageSend;
 
-use crate::state::GlobalState;
+use crate::{actions::Action, permissions::Permissions, state::{ConnectionState, GlobalState}};
 
 
 pub fn message_box(ui: &Ui, state: &mut GlobalState) {
     let selected_channel = state.selected_channel.clone();
-    let http = state.http.clone();
+    let actions = state.actions.clone();
+    let can_send = selected_channel.as_deref()
+        .is_some_and(|channel_id| state.permissions(channel_id).has(Permissions::SEND_MESSAGE));
+
+    let offline = state.connection_state != ConnectionState::Connected;
+
+    if !can_send || offline {
+        let hint = if offline {
+            "You're offline, messages can't be sent until the connection is back"
+        } else {
+            "You don't have permission to send messages here"
+        };
+
+        ui.disabled(true, || {
+            let mut empty = String::new();
+
+            ui.input_text("##textinput", &mut empty)
+                .hint(hint)
+                .read_only(true)
+                .build();
+        });
+
+        return
+    };
 
     let context = state.new_context("MessageBox");
     let current_message = context.use_hook(String::new);
@@ -20,24 +43,19 @@ pub fn message_box(ui: &Ui, state: &mut GlobalState) {
 
     if (ui.button("Send") || should_send) && !current_message.is_empty() {
         if let Some(channel_id) = selected_channel {
-            tokio::spawn({
-                let current_message = current_message.clone();
-
-                async move {
-                    http.send_message(&channel_id, &DataMessageSend {
-                        content: Some(current_message),
-                        nonce: None,
-                        attachments: None,
-                        replies: None,
-                        embeds: None,
-                        masquerade: None,
-                        interactions: None,
-                        flags: None,
-                    }).await.unwrap();
-                }
+            actions.dispatch(Action::SendMessage {
+                channel: channel_id,
+                body: DataMessageSend {
+                    content: Some(std::mem::take(current_message)),
+                    nonce: None,
+                    attachments: None,
+                    replies: None,
+                    embeds: None,
+                    masquerade: None,
+                    interactions: None,
+                    flags: None,
+                },
             });
-            // Send Message Request Here
-            current_message.clear();
         }
     }
 }
\ No newline at end of file
@@ -2,8 +2,30 @@ mod server_list;
 mod channel;
 mod members;
 mod message_box;
+mod attachment;
+mod embed;
+mod media;
+mod roles;
+mod moderation;
+mod notifications;
+mod status;
+mod login;
+mod account;
+mod bot;
+mod text;
 
 pub use server_list::*;
 pub use channel::*;
 pub use members::*;
-pub use message_box::*;
\ No newline at end of file
+pub use message_box::*;
+pub use attachment::*;
+pub use embed::*;
+pub use media::*;
+pub use roles::*;
+pub use moderation::*;
+pub use notifications::*;
+pub use status::*;
+pub use login::*;
+pub use account::*;
+pub use bot::*;
+pub use text::*;
@@ -0,0 +1,214 @@
+use imgui::Ui;
+
+use crate::{actions::Action, http::EditMember, names::{display_name, Author}, permissions::{is_timed_out, Permissions}, state::{GlobalState, ModerationDialog, ModerationKind}, time::{self, Clock}};
+
+const TIMEOUT_PRESETS: &[(i64, &str)] = &[
+    (60, "60 Seconds"),
+    (5 * 60, "5 Minutes"),
+    (10 * 60, "10 Minutes"),
+    (60 * 60, "1 Hour"),
+    (24 * 60 * 60, "1 Day"),
+    (7 * 24 * 60 * 60, "1 Week"),
+];
+
+const CUSTOM_UNITS: &[(i64, &str)] = &[
+    (60, "Minutes"),
+    (60 * 60, "Hours"),
+    (24 * 60 * 60, "Days"),
+];
+
+/// Longest timeout the server accepts.
+const MAX_TIMEOUT_SECONDS: i64 = 28 * 24 * 60 * 60;
+
+/// Messages older than this can't be bulk deleted.
+const BULK_DELETE_MAX_AGE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+pub fn required_permission(kind: ModerationKind) -> Permissions {
+    match kind {
+        ModerationKind::Ban => Permissions::BAN_MEMBERS,
+        ModerationKind::Kick => Permissions::KICK_MEMBERS,
+        ModerationKind::Timeout => Permissions::TIMEOUT_MEMBERS,
+    }
+}
+
+fn timeout_seconds(dialog: &ModerationDialog) -> i64 {
+    if dialog.custom {
+        (CUSTOM_UNITS[dialog.custom_unit].0 * dialog.custom_amount as i64).clamp(1, MAX_TIMEOUT_SECONDS)
+    } else {
+        dialog.duration
+    }
+}
+
+/// Loaded messages from the last week by `user_id` in channels of the server we can delete messages in.
+fn recent_messages(state: &GlobalState, server_id: &str, user_id: &str) -> Vec<(String, Vec<String>)> {
+    let Some(server) = state.servers.get(server_id) else { return Vec::new() };
+    let cutoff = Clock::now_ms() - BULK_DELETE_MAX_AGE_MS;
+
+    server.channels.iter()
+        .filter(|channel_id| state.permissions(channel_id).has(Permissions::MANAGE_MESSAGES))
+        .filter_map(|channel_id| {
+            let ids = state.messages.get(channel_id)?
+                .iter()
+                .filter(|message| message.author == user_id && time::id_timestamp_ms(&message.id) > cutoff)
+                .map(|message| message.id.clone())
+                .collect::<Vec<_>>();
+
+            (!ids.is_empty()).then(|| (channel_id.clone(), ids))
+        })
+        .collect()
+}
+
+fn execute(state: &GlobalState, dialog: &mut ModerationDialog) {
+    let server = dialog.server_id.clone();
+    let user = dialog.user_id.clone();
+
+    let actions = match dialog.kind {
+        ModerationKind::Ban => {
+            let reason = Some(dialog.reason.trim().to_string()).filter(|reason| !reason.is_empty());
+            let messages = if dialog.delete_messages { recent_messages(state, &server, &user) } else { Vec::new() };
+
+            std::iter::once(Action::Ban { server, user, reason })
+                .chain(messages.into_iter().map(|(channel, ids)| Action::DeleteMessages { channel, ids }))
+                .collect()
+        },
+        ModerationKind::Kick => vec![Action::Kick { server, user }],
+        ModerationKind::Timeout => {
+            let body = EditMember {
+                timeout: Some(time::iso8601(Clock::now_ms() + timeout_seconds(dialog) * 1000)),
+                ..Default::default()
+            };
+
+            vec![Action::EditMember { server, user, body }]
+        }
+    };
+
+    dialog.outcome = None;
+    dialog.pending = actions.into_iter().map(|action| state.actions.dispatch(action)).collect();
+}
+
+fn lift_timeout(state: &GlobalState, dialog: &mut ModerationDialog) {
+    let body = EditMember { remove: Some(vec!["Timeout"]), ..Default::default() };
+
+    dialog.outcome = None;
+    dialog.pending = vec![state.actions.dispatch(Action::EditMember { server: dialog.server_id.clone(), user: dialog.user_id.clone(), body })];
+}
+
+fn timeout_options(ui: &Ui, dialog: &mut ModerationDialog) {
+    for (seconds, label) in TIMEOUT_PRESETS {
+        if ui.radio_button_bool(label, !dialog.custom && dialog.duration == *seconds) {
+            dialog.custom = false;
+            dialog.duration = *seconds;
+        }
+    }
+
+    if ui.radio_button_bool("Custom", dialog.custom) {
+        dialog.custom = true;
+    };
+
+    if dialog.custom {
+        let _width = ui.push_item_width(100.0);
+
+        ui.input_int("##amount", &mut dialog.custom_amount).build();
+
+        ui.same_line();
+
+        let units = CUSTOM_UNITS.iter().map(|(_, label)| *label).collect::<Vec<_>>();
+        ui.combo_simple_string("##unit", &mut dialog.custom_unit, &units);
+
+        let max_amount = (MAX_TIMEOUT_SECONDS / CUSTOM_UNITS[dialog.custom_unit].0) as i32;
+        dialog.custom_amount = dialog.custom_amount.clamp(1, max_amount);
+
+        ui.text_disabled("Timeouts can last up to 28 days");
+    };
+}
+
+pub fn moderation_dialog(ui: &Ui, state: &mut GlobalState) {
+    let Some(mut dialog) = state.moderation.take() else { return };
+
+    let title = dialog.kind.title();
+
+    if !dialog.opened {
+        ui.open_popup(title);
+        dialog.opened = true;
+    };
+
+    let name = display_name(state, Some(&dialog.server_id), Author::Id(&dialog.user_id));
+    let allowed = state.can_moderate(&dialog.server_id, &dialog.user_id, required_permission(dialog.kind));
+    let timed_out = state.members.get(&dialog.server_id)
+        .and_then(|members| members.get(&dialog.user_id))
+        .is_some_and(is_timed_out);
+
+    let mut close = false;
+
+    let shown = ui.modal_popup_config(title)
+        .always_auto_resize(true)
+        .build(|| {
+            match dialog.kind {
+                ModerationKind::Ban => {
+                    ui.text(format!("Are you sure you want to ban {name}?"));
+                    ui.input_text_multiline("Reason", &mut dialog.reason, [300.0, 60.0]).build();
+                    ui.checkbox("Delete their loaded messages from the past week", &mut dialog.delete_messages);
+
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Only messages already loaded in open channels are deleted.");
+                    };
+                },
+                ModerationKind::Kick => {
+                    ui.text(format!("Are you sure you want to kick {name}?"));
+                    ui.text_disabled("They will be able to rejoin with a new invite.");
+                },
+                ModerationKind::Timeout => {
+                    ui.text(format!("Timeout {name} for"));
+                    timeout_options(ui, &mut dialog);
+
+                    if timed_out {
+                        ui.text_disabled("This member is currently timed out.");
+                    };
+                }
+            };
+
+            ui.separator();
+
+            match &dialog.outcome {
+                Some(Ok(())) => ui.text_colored([0.3, 0.8, 0.4, 1.0], "Done"),
+                Some(Err(e)) => ui.text_colored([0.9, 0.3, 0.3, 1.0], format!("Failed: {e}")),
+                None if !dialog.pending.is_empty() => ui.text_disabled("Sending..."),
+                None => {}
+            };
+
+            if let Err(reason) = allowed {
+                ui.text_disabled(reason);
+            };
+
+            let finished = matches!(dialog.outcome, Some(Ok(())));
+
+            ui.disabled(allowed.is_err() || !dialog.pending.is_empty() || finished, || {
+                if ui.button(title) {
+                    execute(state, &mut dialog);
+                };
+
+                if dialog.kind == ModerationKind::Timeout && timed_out {
+                    ui.same_line();
+
+                    if ui.button("Remove Timeout") {
+                        lift_timeout(state, &mut dialog);
+                    }
+                };
+            });
+
+            ui.same_line();
+
+            if ui.button(if finished { "Close" } else { "Cancel" }) {
+                for id in dialog.pending.drain(..) {
+                    state.actions.cancel(id);
+                };
+
+                close = true;
+                ui.close_current_popup();
+            };
+        });
+
+    if shown.is_some() && !close {
+        state.moderation = Some(dialog);
+    }
+}
@@ -0,0 +1,139 @@
+use imgui::{Condition, StyleColor, TableFlags, Ui, WindowFlags};
+
+use crate::{components::status_bar_height, notifications::Level, state::GlobalState};
+
+const SUCCESS_COLOUR: [f32; 4] = [0.24, 0.75, 0.4, 1.0];
+const ERROR_COLOUR: [f32; 4] = [0.93, 0.27, 0.27, 1.0];
+const TOAST_MARGIN: f32 = 12.0;
+const TOAST_WIDTH: f32 = 320.0;
+
+/// Stack of transient notifications in the bottom right corner of the main viewport, clicking one dismisses it
+/// and opens the problems log for errors.
+pub fn toasts(ui: &Ui, state: &mut GlobalState) {
+    if state.notifications.toasts.is_empty() {
+        return
+    };
+
+    let viewport = ui.main_viewport();
+    let corner = [
+        viewport.work_pos[0] + viewport.work_size[0] - TOAST_MARGIN,
+        viewport.work_pos[1] + viewport.work_size[1] - status_bar_height(ui) - TOAST_MARGIN,
+    ];
+
+    let flags = WindowFlags::NO_DECORATION
+        | WindowFlags::ALWAYS_AUTO_RESIZE
+        | WindowFlags::NO_SAVED_SETTINGS
+        | WindowFlags::NO_FOCUS_ON_APPEARING
+        | WindowFlags::NO_NAV
+        | WindowFlags::NO_DOCKING;
+
+    let mut dismissed = None;
+
+    ui.window("##Toasts")
+        .position(corner, Condition::Always)
+        .position_pivot([1.0, 1.0])
+        .bg_alpha(0.9)
+        .flags(flags)
+        .build(|| {
+            for (index, toast) in state.notifications.toasts.iter().enumerate() {
+                let _id = ui.push_id_usize(index);
+                let colour = match toast.notification.level {
+                    Level::Success => SUCCESS_COLOUR,
+                    Level::Error => ERROR_COLOUR,
+                };
+
+                if index > 0 {
+                    ui.separator();
+                };
+
+                ui.group(|| {
+                    let _wrap = ui.push_text_wrap_pos_with_pos(ui.cursor_pos()[0] + TOAST_WIDTH);
+                    let _colour = ui.push_style_color(StyleColor::Text, colour);
+
+                    ui.text_wrapped(&toast.notification.message);
+                });
+
+                if ui.is_item_clicked() {
+                    dismissed = Some((index, toast.notification.level));
+                };
+
+                if ui.is_item_hovered() && toast.notification.level == Level::Error {
+                    ui.tooltip_text("Click to view details");
+                };
+            }
+        });
+
+    if let Some((index, level)) = dismissed {
+        state.notifications.toasts.remove(index);
+
+        if level == Level::Error {
+            state.notifications.problems_open = true;
+        };
+    };
+}
+
+/// Every error so far with its details, newest first.
+pub fn problems(ui: &Ui, state: &mut GlobalState) {
+    if !state.notifications.problems_open {
+        return
+    };
+
+    let mut opened = true;
+    let mut clear = false;
+
+    ui.window(format!("Problems ({})###Problems", state.notifications.problems.len()))
+        .opened(&mut opened)
+        .size([600.0, 400.0], Condition::FirstUseEver)
+        .build(|| {
+            if ui.button("Clear") {
+                clear = true;
+            };
+
+            ui.same_line();
+
+            if ui.button("Copy All") {
+                let text = state.notifications.problems.iter()
+                    .map(|problem| format!("[{}] {}: {}", state.clock.full(problem.at_ms, state.settings.clock_format), problem.message, problem.details))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                ui.set_clipboard_text(text);
+            };
+
+            if state.notifications.problems.is_empty() {
+                ui.text_disabled("No problems");
+                return
+            };
+
+            if let Some(_table) = ui.begin_table_with_flags("ProblemList", 2, TableFlags::BORDERS_INNER_H | TableFlags::ROW_BG | TableFlags::SCROLL_Y) {
+                ui.table_setup_column("Time");
+                ui.table_setup_column("Problem");
+                ui.table_headers_row();
+
+                for (index, problem) in state.notifications.problems.iter().enumerate().rev() {
+                    let _id = ui.push_id_usize(index);
+                    let datetime = state.clock.from_millis(problem.at_ms);
+
+                    ui.table_next_row();
+                    ui.table_next_column();
+                    ui.text_disabled(state.clock.time_with_seconds(&datetime, state.settings.clock_format));
+
+                    ui.table_next_column();
+
+                    if let Some(_node) = ui.tree_node(&problem.message) {
+                        ui.text_wrapped(&problem.details);
+
+                        if ui.small_button("Copy") {
+                            ui.set_clipboard_text(&problem.details);
+                        };
+                    };
+                }
+            };
+        });
+
+    if clear {
+        state.notifications.problems.clear();
+    };
+
+    state.notifications.problems_open = opened;
+}
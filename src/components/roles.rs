@@ -0,0 +1,252 @@
+use imgui::{Condition, DragDropFlags, StyleColor, TableFlags, Ui};
+use revolt_models::v0::Role;
+
+use crate::{actions::Action, colour::{parse_css_colour, to_hex}, http::EditRole, permissions::Permissions, state::{GlobalState, RoleDraft, RoleEditor}};
+
+fn draft_from_role(role: &Role) -> RoleDraft {
+    RoleDraft {
+        name: role.name.clone(),
+        colour: role.colour.as_deref()
+            .and_then(parse_css_colour)
+            .map(|[r, g, b, _]| [r, g, b]),
+        hoist: role.hoist,
+        allow: role.permissions.a as u64,
+        deny: role.permissions.d as u64,
+    }
+}
+
+/// Keeps the drag order in sync with roles being created or deleted elsewhere.
+fn sync_order(editor: &mut RoleEditor, state: &GlobalState) {
+    let Some(server) = state.servers.get(&editor.server_id) else { return };
+
+    let up_to_date = editor.order.len() == server.roles.len()
+        && editor.order.iter().all(|id| server.roles.contains_key(id));
+
+    if !up_to_date {
+        let mut roles = server.roles.iter().collect::<Vec<_>>();
+        roles.sort_by_key(|(_, role)| role.rank);
+
+        editor.order = roles.into_iter().map(|(id, _)| id.clone()).collect();
+        editor.saved_order = editor.order.clone();
+    }
+}
+
+/// Hands the ranks of the roles below our own back out in their new order, ranks have gaps once roles are created
+/// or deleted so only roles whose rank actually changes are edited.
+fn save_order(state: &GlobalState, editor: &mut RoleEditor, own_rank: i64) {
+    let Some(server) = state.servers.get(&editor.server_id) else { return };
+
+    let movable = editor.order.iter()
+        .filter_map(|id| server.roles.get(id).map(|role| (id, role.rank)))
+        .filter(|(_, rank)| *rank > own_rank)
+        .collect::<Vec<_>>();
+
+    let mut ranks = movable.iter().map(|(_, rank)| *rank).collect::<Vec<_>>();
+    ranks.sort();
+
+    for ((role_id, rank), new_rank) in movable.into_iter().zip(ranks) {
+        if rank != new_rank {
+            state.actions.dispatch(Action::EditRole {
+                server: editor.server_id.clone(),
+                role: role_id.clone(),
+                body: EditRole { rank: Some(new_rank), ..Default::default() },
+            });
+        }
+    }
+
+    editor.saved_order = editor.order.clone();
+}
+
+fn save_role(state: &GlobalState, server_id: &str, role_id: &str, draft: &RoleDraft) {
+    let body = EditRole {
+        name: Some(draft.name.clone()),
+        colour: draft.colour.map(to_hex),
+        hoist: Some(draft.hoist),
+        rank: None,
+        remove: draft.colour.is_none().then(|| vec!["Colour"]),
+    };
+
+    state.actions.dispatch(Action::EditRole { server: server_id.to_string(), role: role_id.to_string(), body });
+    state.actions.dispatch(Action::SetRolePermissions {
+        server: server_id.to_string(),
+        role: role_id.to_string(),
+        allow: draft.allow,
+        deny: draft.deny,
+    });
+}
+
+/// Allow / deny checkboxes for every permission, a permission can't be both.
+fn permission_grid(ui: &Ui, draft: &mut RoleDraft) {
+    if let Some(_table) = ui.begin_table_with_flags("Permissions", 3, TableFlags::BORDERS_INNER_H | TableFlags::ROW_BG) {
+        ui.table_setup_column("Permission");
+        ui.table_setup_column("Allow");
+        ui.table_setup_column("Deny");
+        ui.table_headers_row();
+
+        for (permission, name) in Permissions::NAMED {
+            let _id = ui.push_id(*name);
+
+            ui.table_next_row();
+            ui.table_next_column();
+            ui.text(name);
+
+            ui.table_next_column();
+            let mut allowed = draft.allow & permission.0 != 0;
+
+            if ui.checkbox("##allow", &mut allowed) {
+                draft.allow ^= permission.0;
+                draft.deny &= !permission.0;
+            };
+
+            ui.table_next_column();
+            let mut denied = draft.deny & permission.0 != 0;
+
+            if ui.checkbox("##deny", &mut denied) {
+                draft.deny ^= permission.0;
+                draft.allow &= !permission.0;
+            };
+        }
+    }
+}
+
+pub fn role_editor(ui: &Ui, state: &mut GlobalState) {
+    let Some(mut editor) = state.role_editor.take() else { return };
+    let Some(server) = state.servers.get(&editor.server_id).cloned() else { return };
+
+    sync_order(&mut editor, state);
+
+    let can_manage = state.server_permissions(&server.id).has(Permissions::MANAGE_ROLE);
+    let own_rank = state.user_id.as_deref()
+        .map(|id| state.member_rank(&server.id, id))
+        .unwrap_or(i64::MAX);
+
+    let mut opened = true;
+
+    ui.window(format!("Roles - {}###RoleEditor", server.name))
+        .opened(&mut opened)
+        .size([700.0, 500.0], Condition::FirstUseEver)
+        .build(|| {
+            ui.child_window("RoleList")
+                .size([200.0, 0.0])
+                .border(true)
+                .build(|| {
+                    let mut moved = None;
+
+                    for (index, role_id) in editor.order.clone().iter().enumerate() {
+                        let Some(role) = server.roles.get(role_id) else { continue };
+                        let colour = role.colour.as_deref().and_then(parse_css_colour);
+
+                        let colour_token = colour.map(|colour| ui.push_style_color(StyleColor::Text, colour));
+                        let clicked = ui.selectable_config(format!("{}##{role_id}", role.name))
+                            .selected(editor.selected.as_ref() == Some(role_id))
+                            .build();
+                        drop(colour_token);
+
+                        if clicked {
+                            editor.selected = Some(role_id.clone());
+                            editor.draft = Some(draft_from_role(role));
+                        };
+
+                        if can_manage && role.rank > own_rank {
+                            if let Some(tooltip) = ui.drag_drop_source_config("ROLE").begin_payload(index) {
+                                ui.text(&role.name);
+                                tooltip.end();
+                            };
+                        };
+
+                        if let Some(target) = ui.drag_drop_target() {
+                            if let Some(Ok(payload)) = target.accept_payload::<usize, _>("ROLE", DragDropFlags::empty()) {
+                                moved = Some((payload.data, index));
+                            };
+
+                            target.pop();
+                        };
+                    }
+
+                    // Roles can't be moved above our own highest role.
+                    if let Some((from, to)) = moved {
+                        let target_rank = server.roles.get(&editor.order[to]).map(|role| role.rank).unwrap_or(i64::MAX);
+
+                        if target_rank > own_rank {
+                            let role_id = editor.order.remove(from);
+                            editor.order.insert(to, role_id);
+                        }
+                    };
+
+                    ui.separator();
+
+                    ui.disabled(!can_manage, || {
+                        if ui.button("Create Role") {
+                            state.actions.dispatch(Action::CreateRole { server: server.id.clone(), name: "New Role".to_string() });
+                        };
+
+                        ui.disabled(editor.order == editor.saved_order, || {
+                            if ui.button("Save Order") {
+                                save_order(state, &mut editor, own_rank);
+                            }
+                        });
+                    });
+                });
+
+            ui.same_line();
+
+            ui.child_window("RoleDetails")
+                .build(|| {
+                    let (Some(role_id), Some(draft)) = (editor.selected.clone(), editor.draft.as_mut()) else {
+                        ui.text_disabled("Select a role to edit");
+                        return
+                    };
+
+                    let Some(role) = server.roles.get(&role_id) else {
+                        ui.text_disabled("This role was deleted");
+                        return
+                    };
+
+                    let editable = can_manage && role.rank > own_rank;
+
+                    ui.disabled(!editable, || {
+                        ui.input_text("Name", &mut draft.name).build();
+
+                        let mut has_colour = draft.colour.is_some();
+
+                        if ui.checkbox("Colour", &mut has_colour) {
+                            draft.colour = has_colour.then_some([1.0, 1.0, 1.0]);
+                        };
+
+                        if let Some(colour) = draft.colour.as_mut() {
+                            ui.same_line();
+                            ui.color_edit3("##colour", colour);
+                        };
+
+                        ui.checkbox("Display role members separately", &mut draft.hoist);
+
+                        ui.separator();
+                        permission_grid(ui, draft);
+
+                        if ui.button("Save") {
+                            save_role(state, &server.id, &role_id, draft);
+                        };
+
+                        ui.same_line();
+
+                        if ui.button("Reset") {
+                            *draft = draft_from_role(role);
+                        };
+
+                        ui.same_line();
+
+                        if ui.button("Delete Role") {
+                            state.actions.dispatch(Action::DeleteRole { server: server.id.clone(), role: role_id.clone() });
+                        };
+                    });
+
+                    if !editable {
+                        ui.text_disabled("You can only edit roles ranked below your own.");
+                    };
+                });
+        });
+
+    if opened {
+        state.role_editor = Some(editor);
+    }
+}
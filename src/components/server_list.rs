@@ -1,21 +1,81 @@
-use imgui::Ui;
+use imgui::{StyleColor, Ui};
 use revolt_models::v0::Channel;
 
-use crate::state::GlobalState;
+use crate::{components::{avatar, icon}, state::{GlobalState, UnreadSummary}};
 
+const ICON_SIZE: f32 = 16.0;
+const MENTION_COLOUR: [f32; 4] = [0.93, 0.27, 0.27, 1.0];
 
-pub fn channel_button(ui: &Ui, state: &mut GlobalState, server_id: &str, channel_id: &str) {
-    if let Some(channel) = state.channels.get(channel_id) {
-        let name = match channel {
-            Channel::SavedMessages { .. } => "Saved Messages",
-            Channel::DirectMessage { .. } => "DM TODO",
-            Channel::Group { name, .. } | Channel::TextChannel { name, .. } | Channel::VoiceChannel { name, .. } => name,
+/// Dims the text of whatever is drawn inside `f` unless it has unread messages.
+fn with_unread_style<R>(ui: &Ui, summary: UnreadSummary, f: impl FnOnce() -> R) -> R {
+    let _colour = (!summary.unread).then(|| ui.push_style_color(StyleColor::Text, ui.style_color(StyleColor::TextDisabled)));
+
+    f()
+}
+
+/// Draws a mention count over the right edge of the last item.
+fn mention_badge(ui: &Ui, summary: UnreadSummary) {
+    if summary.mentions == 0 {
+        return
+    };
+
+    let (min, max) = (ui.item_rect_min(), ui.item_rect_max());
+    let text = summary.mentions.to_string();
+    let text_size = ui.calc_text_size(&text);
+    let height = max[1] - min[1];
+    let width = (text_size[0] + 8.0).max(height);
+    let pos = [max[0] - width, min[1]];
+
+    let draw_list = ui.get_window_draw_list();
+
+    draw_list
+        .add_rect(pos, max, MENTION_COLOUR)
+        .filled(true)
+        .rounding(height / 2.0)
+        .build();
+
+    draw_list.add_text(
+        [pos[0] + (width - text_size[0]) / 2.0, pos[1] + (height - text_size[1]) / 2.0],
+        [1.0, 1.0, 1.0, 1.0],
+        &text,
+    );
+}
+
+
+pub fn channel_button(ui: &Ui, state: &mut GlobalState, server_id: Option<&str>, channel_id: &str) {
+    if let Some(channel) = state.channels.get(channel_id).cloned() {
+        let mut name = state.channel_name(&channel);
+
+        match &channel {
+            Channel::DirectMessage { .. } => {
+                if let Some(user) = state.dm_recipient(&channel).cloned() {
+                    avatar(ui, state, &user, None, ICON_SIZE);
+                    ui.same_line();
+                }
+            },
+            Channel::Group { icon: group_icon, recipients, .. } => {
+                icon(ui, state, group_icon.as_ref(), &name, ICON_SIZE);
+                ui.same_line();
+
+                name = format!("{name} ({} members)", recipients.len());
+            },
+            Channel::SavedMessages { .. } => {
+                if let Some(user) = state.user_id.as_ref().and_then(|id| state.users.get(id)).cloned() {
+                    avatar(ui, state, &user, None, ICON_SIZE);
+                    ui.same_line();
+                }
+            },
+            _ => {}
         };
 
-        if ui.selectable(name) {
+        let summary = state.unread_summary(channel_id);
+
+        if with_unread_style(ui, summary, || ui.selectable(format!("{name}##{channel_id}"))) {
             state.selected_channel = Some(channel_id.to_string());
-            state.selected_server = Some(server_id.to_string());
+            state.selected_server = server_id.map(|id| id.to_string());
         };
+
+        mention_badge(ui, summary);
     }
 }
 
@@ -35,28 +95,43 @@ pub fn server_list(ui: &Ui, state: &mut GlobalState) {
         ui.tree_node_config("Direct Messages")
         .default_open(false)
         .build(|| {
-
+            for channel_id in state.direct_message_channels() {
+                channel_button(ui, state, None, &channel_id)
+            }
         });
 
         ui.tree_node_config("Servers")
         .default_open(true)
         .build(|| {
             for server in state.servers.clone().values() {
-                if let Some(token) = ui.tree_node(&server.name) {
+                icon(ui, state, server.icon.as_ref(), &server.name, ICON_SIZE);
+                ui.same_line();
+
+                let summary = state.channels_unread_summary(&server.channels);
+                let token = with_unread_style(ui, summary, || ui.tree_node_config(format!("{}###{}", server.name, server.id)).push());
+
+                mention_badge(ui, summary);
+
+                if let Some(token) = token {
                     let categories = server.categories.clone().unwrap_or_default();
                     let channels = &server.channels;
 
                     for channel_id in channels {
                         if !categories.iter().any(|c| c.channels.contains(channel_id)) {
 
-                            channel_button(ui, state, &server.id, channel_id)
+                            channel_button(ui, state, Some(&server.id), channel_id)
                         }
                     }
 
                     for category in categories {
-                        if let Some(cat_token) = ui.tree_node(category.title) {
+                        let summary = state.channels_unread_summary(&category.channels);
+                        let cat_token = with_unread_style(ui, summary, || ui.tree_node_config(format!("{}###{}", category.title, category.id)).push());
+
+                        mention_badge(ui, summary);
+
+                        if let Some(cat_token) = cat_token {
                             for channel_id in &category.channels {
-                                channel_button(ui, state, &server.id, channel_id)
+                                channel_button(ui, state, Some(&server.id), channel_id)
                             }
 
                             cat_token.end()
@@ -0,0 +1,90 @@
+use imgui::{Condition, StyleColor, StyleVar, Ui, WindowFlags};
+
+use crate::state::{ConnectionState, GlobalState};
+
+const CONNECTED_COLOUR: [f32; 4] = [0.24, 0.75, 0.4, 1.0];
+const CONNECTING_COLOUR: [f32; 4] = [0.96, 0.65, 0.14, 1.0];
+const DISCONNECTED_COLOUR: [f32; 4] = [0.93, 0.27, 0.27, 1.0];
+
+fn describe(connection_state: ConnectionState) -> (String, [f32; 4]) {
+    match connection_state {
+        ConnectionState::Connecting => ("Connecting...".to_string(), CONNECTING_COLOUR),
+        ConnectionState::Connected => ("Connected".to_string(), CONNECTED_COLOUR),
+        ConnectionState::Disconnected => ("Disconnected".to_string(), DISCONNECTED_COLOUR),
+        ConnectionState::Reconnecting { attempt } => (format!("Reconnecting (attempt {attempt})..."), CONNECTING_COLOUR),
+    }
+}
+
+/// Height the status bar takes away from the bottom of the main viewport.
+pub fn status_bar_height(ui: &Ui) -> f32 {
+    ui.frame_height()
+}
+
+/// Thin bar along the bottom of the main viewport with the gateway state, latency and instance details.
+pub fn status_bar(ui: &Ui, state: &mut GlobalState) {
+    let viewport = ui.main_viewport();
+    let height = status_bar_height(ui);
+
+    let flags = WindowFlags::NO_DECORATION
+        | WindowFlags::NO_MOVE
+        | WindowFlags::NO_SAVED_SETTINGS
+        | WindowFlags::NO_FOCUS_ON_APPEARING
+        | WindowFlags::NO_NAV
+        | WindowFlags::NO_DOCKING
+        | WindowFlags::NO_SCROLLBAR;
+
+    let _padding = ui.push_style_var(StyleVar::WindowPadding([8.0, 0.0]));
+    let _rounding = ui.push_style_var(StyleVar::WindowRounding(0.0));
+
+    ui.window("##StatusBar")
+        .position([viewport.work_pos[0], viewport.work_pos[1] + viewport.work_size[1] - height], Condition::Always)
+        .size([viewport.work_size[0], height], Condition::Always)
+        .flags(flags)
+        .build(|| {
+            ui.set_cursor_pos([ui.cursor_pos()[0], (height - ui.text_line_height()) / 2.0]);
+
+            let (label, colour) = describe(state.connection_state);
+            ui.text_colored(colour, "\u{25CF}");
+            ui.same_line();
+            ui.text(label);
+
+            if let Some(latency_ms) = state.latency_ms {
+                ui.same_line();
+                ui.text_disabled(format!("{latency_ms} ms"));
+            };
+
+            ui.same_line();
+            ui.text_disabled("|");
+            ui.same_line();
+            ui.text(state.api_base());
+
+            let build = &state.config.build;
+
+            ui.same_line();
+            ui.text_disabled("|");
+            ui.same_line();
+            ui.text(format!("Revolt {}", build.semver));
+
+            if ui.is_item_hovered() {
+                ui.tooltip_text(format!("Commit {}\nBuilt {}", build.commit_sha, build.timestamp));
+            };
+        });
+}
+
+/// Banner shown above the channel while the gateway isn't connected.
+pub fn connection_banner(ui: &Ui, state: &GlobalState) {
+    if state.connection_state == ConnectionState::Connected {
+        return
+    };
+
+    let (label, colour) = describe(state.connection_state);
+
+    let _colour = ui.push_style_color(StyleColor::ChildBg, [colour[0], colour[1], colour[2], 0.25]);
+
+    ui.child_window("##ConnectionBanner")
+        .size([0.0, ui.frame_height_with_spacing() * 1.5])
+        .build(|| {
+            ui.text_colored(colour, label);
+            ui.text_wrapped("Messages won't arrive and can't be sent until the connection is back.");
+        });
+}
@@ -0,0 +1,41 @@
+use imgui::{StyleColor, StyleVar, Ui};
+
+use crate::markup::Span;
+
+/// Wrapped text made of differently coloured spans, imgui only wraps text drawn in one go so this wraps word by word.
+pub fn rich_text(ui: &Ui, spans: &[Span]) {
+    let text_colour = ui.style_color(StyleColor::Text);
+    let line_spacing = ui.clone_style().item_spacing[1];
+    let _spacing = ui.push_style_var(StyleVar::ItemSpacing([0.0, line_spacing]));
+
+    let right = ui.cursor_screen_pos()[0] + ui.content_region_avail()[0];
+    let mut line_empty = true;
+
+    for span in spans {
+        let colour = span.colour.unwrap_or(text_colour);
+
+        for (i, line) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                // Two newlines in a row leave a blank line.
+                if line_empty {
+                    ui.new_line();
+                };
+
+                line_empty = true;
+            };
+
+            for word in line.split_inclusive(' ') {
+                if !line_empty {
+                    ui.same_line();
+
+                    if ui.cursor_screen_pos()[0] + ui.calc_text_size(word.trim_end())[0] > right {
+                        ui.new_line();
+                    };
+                };
+
+                ui.text_colored(colour, word);
+                line_empty = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use reqwest::StatusCode;
+
+use crate::actions::{Action, ActionError, ActionId, Actions, Output};
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Something fetched on demand, only one request per key is ever in flight.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum FetchKey {
+    User(String),
+    Profile(String),
+    Member { server: String, user: String },
+    AllMembers(String),
+}
+
+enum Failure {
+    /// The user no longer exists or left the server, these aren't retried.
+    Permanent,
+    /// Timeouts and server errors, retried once `retry_at` has passed with a longer wait after every failure.
+    Transient { attempts: u32, retry_at: Instant },
+}
+
+/// Tracks fetches of users and members missing from the cache, the results are applied with the rest of the
+/// action results.
+pub struct Fetcher {
+    in_flight: HashMap<ActionId, FetchKey>,
+    failed: HashMap<FetchKey, Failure>,
+}
+
+impl Fetcher {
+    pub fn new() -> Self {
+        Self {
+            in_flight: HashMap::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    pub fn is_loading(&self, key: &FetchKey) -> bool {
+        self.in_flight.values().any(|k| k == key)
+    }
+
+    pub fn request(&mut self, actions: &Actions, key: FetchKey) {
+        if self.is_loading(&key) {
+            return
+        };
+
+        match self.failed.get(&key) {
+            Some(Failure::Permanent) => return,
+            Some(Failure::Transient { retry_at, .. }) if Instant::now() < *retry_at => return,
+            _ => {}
+        };
+
+        let action = match &key {
+            FetchKey::User(id) => Action::FetchUser(id.clone()),
+            FetchKey::Profile(id) => Action::FetchProfile(id.clone()),
+            FetchKey::Member { server, user } => Action::FetchMember { server: server.clone(), user: user.clone() },
+            FetchKey::AllMembers(server) => Action::FetchMembers(server.clone()),
+        };
+
+        self.in_flight.insert(actions.dispatch(action), key);
+    }
+
+    pub fn has_failed(&self, key: &FetchKey) -> bool {
+        self.failed.contains_key(key)
+    }
+
+    /// Called for every finished action, ignores those which weren't fetches.
+    pub fn finished(&mut self, id: ActionId, result: &Result<Output, ActionError>) {
+        let Some(key) = self.in_flight.remove(&id) else { return };
+
+        let error = match result {
+            Ok(_) => {
+                self.failed.remove(&key);
+                return
+            },
+            Err(e) if e.cancelled => return,
+            Err(e) => e
+        };
+
+        let failure = if matches!(error.status, Some(StatusCode::NOT_FOUND | StatusCode::FORBIDDEN)) {
+            Failure::Permanent
+        } else {
+            let attempts = match self.failed.get(&key) {
+                Some(Failure::Transient { attempts, .. }) => attempts + 1,
+                _ => 1
+            };
+
+            let delay = (RETRY_DELAY * 2u32.pow(attempts.min(8) - 1)).min(MAX_RETRY_DELAY);
+
+            Failure::Transient { attempts, retry_at: Instant::now() + delay }
+        };
+
+        self.failed.insert(key, failure);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http::{HttpClient, TokenKind}, mock_server::MockServer};
+
+    fn error(status: Option<StatusCode>) -> Result<Output, ActionError> {
+        Err(ActionError { action: "Fetching a user", message: "failed".to_string(), status, cancelled: false, background: true })
+    }
+
+    fn fetch_once(fetcher: &mut Fetcher, actions: &Actions, key: &FetchKey) -> ActionId {
+        fetcher.request(actions, key.clone());
+
+        *fetcher.in_flight.keys().next().expect("nothing was dispatched")
+    }
+
+    #[tokio::test]
+    async fn error_status_is_kept() {
+        let server = MockServer::start(vec![(404, r#"{"type":"NotFound"}"#)]);
+        let http = HttpClient::new(server.base.clone(), "token".to_string(), TokenKind::Session);
+
+        let error = http.fetch_user("01ABC").await.unwrap_err();
+
+        assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn missing_users_are_not_retried() {
+        let (actions, _service) = Actions::new();
+        let mut fetcher = Fetcher::new();
+        let key = FetchKey::User("01ABC".to_string());
+
+        for status in [StatusCode::NOT_FOUND, StatusCode::FORBIDDEN] {
+            let id = fetch_once(&mut fetcher, &actions, &key);
+            fetcher.finished(id, &error(Some(status)));
+
+            assert!(matches!(fetcher.failed.get(&key), Some(Failure::Permanent)));
+
+            fetcher.request(&actions, key.clone());
+            assert!(!fetcher.is_loading(&key));
+
+            fetcher.failed.clear();
+        }
+    }
+
+    #[test]
+    fn other_failures_back_off() {
+        let (actions, _service) = Actions::new();
+        let mut fetcher = Fetcher::new();
+        let key = FetchKey::User("01ABC".to_string());
+
+        for status in [Some(StatusCode::INTERNAL_SERVER_ERROR), None] {
+            let id = fetch_once(&mut fetcher, &actions, &key);
+            fetcher.finished(id, &error(status));
+
+            assert!(matches!(fetcher.failed.get(&key), Some(Failure::Transient { .. })));
+
+            // Still waiting out the delay.
+            fetcher.request(&actions, key.clone());
+            assert!(!fetcher.is_loading(&key));
+
+            if let Some(Failure::Transient { retry_at, .. }) = fetcher.failed.get_mut(&key) {
+                *retry_at = Instant::now();
+            };
+        }
+
+        let Some(Failure::Transient { attempts, .. }) = fetcher.failed.get(&key) else { unreachable!() };
+        assert_eq!(*attempts, 2);
+
+        let id = fetch_once(&mut fetcher, &actions, &key);
+        fetcher.finished(id, &Ok(Output::None));
+
+        assert!(!fetcher.has_failed(&key));
+    }
+
+    #[test]
+    fn cancelled_fetches_are_not_failures() {
+        let (actions, _service) = Actions::new();
+        let mut fetcher = Fetcher::new();
+        let key = FetchKey::AllMembers("01SERVER".to_string());
+
+        let id = fetch_once(&mut fetcher, &actions, &key);
+        let cancelled = ActionError { action: "Fetching members", message: "Cancelled".to_string(), status: None, cancelled: true, background: true };
+        fetcher.finished(id, &Err(cancelled));
+
+        assert!(!fetcher.has_failed(&key));
+    }
+}
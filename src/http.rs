@@ -1,7 +1,9 @@
 use reqwest::{Client, Method};
-use revolt_models::v0::{DataMessageSend, Message};
+use revolt_models::v0::{AllMemberResponse, BulkMessageResponse, ChannelUnread, DataMessageSend, File, Member, Message, Role, User, UserProfile};
 use serde::{Deserialize, Serialize};
-use futures::TryFutureExt;
+
+/// How many messages are fetched at a time when loading history.
+pub const HISTORY_PAGE_SIZE: usize = 50;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct CaptchaFeature {
@@ -51,33 +53,202 @@ pub struct RevoltConfig {
     pub build: BuildInformation,
 }
 
+impl RevoltConfig {
+    pub fn autumn_url(&self, file: &File) -> String {
+        format!("{}/{}/{}", self.features.autumn.url, file.tag, file.id)
+    }
+
+    /// Asks autumn for a version of the file no larger than `max_side` on either axis.
+    pub fn autumn_url_sized(&self, file: &File, max_side: u32) -> String {
+        format!("{}?max_side={max_side}", self.autumn_url(file))
+    }
+
+    /// Routes external media through january so remote hosts never see the client.
+    pub fn january_proxy_url(&self, url: &str) -> String {
+        reqwest::Url::parse_with_params(&format!("{}/proxy", self.features.january.url), &[("url", url)])
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| url.to_string())
+    }
+}
+
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct EditMember {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    /// ISO 8601 timestamp the member is timed out until.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove: Option<Vec<&'static str>>,
+}
+
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct EditRole {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hoist: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove: Option<Vec<&'static str>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewRole {
+    pub id: String,
+    pub role: Role,
+}
+
+/// Which header the token is sent in, bots authenticate differently to users.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+    Session,
+    Bot,
+}
+
+impl TokenKind {
+    pub fn header(self) -> &'static str {
+        match self {
+            TokenKind::Session => "x-session-token",
+            TokenKind::Bot => "x-bot-token",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct HttpClient {
     pub base: String,
     pub token: String,
+    pub kind: TokenKind,
     pub inner: Client
 }
 
 impl HttpClient {
-    pub fn new(base: String, token: String) -> Self {
-        HttpClient { base, token, inner: Client::new() }
+    pub fn new(base: String, token: String, kind: TokenKind) -> Self {
+        HttpClient { base, token, kind, inner: Client::new() }
     }
 
     async fn request<I: Serialize, O: for<'a> Deserialize<'a>>(&self, method: Method, route: impl AsRef<str>, body: Option<&I>) -> Result<O, reqwest::Error> {
         let mut builder = self.inner.request(method, format!("{}{}", &self.base, route.as_ref()))
-            .header("x-session-token", &self.token);
+            .header(self.kind.header(), &self.token);
+
+        if let Some(body) = body {
+            builder = builder.json(body)
+        };
+
+        builder.send().await?.error_for_status()?.json().await
+    }
+
+    /// Same as [`HttpClient::request`] for routes that respond with no body.
+    async fn request_empty<I: Serialize>(&self, method: Method, route: impl AsRef<str>, body: Option<&I>) -> Result<(), reqwest::Error> {
+        let mut builder = self.inner.request(method, format!("{}{}", &self.base, route.as_ref()))
+            .header(self.kind.header(), &self.token);
 
         if let Some(body) = body {
             builder = builder.json(body)
         };
 
-        builder.send()
-            .and_then(|resp| {
-                resp.json()
-            }).await
+        builder.send().await?.error_for_status()?;
+
+        Ok(())
     }
 
     pub async fn send_message(&self, channel: &str, body: &DataMessageSend) -> Result<Message, reqwest::Error> {
         self.request(Method::POST, format!("/channels/{channel}/messages"), Some(body)).await
     }
+
+    /// A page of messages in a channel along with their authors, the latest unless `before` is given.
+    pub async fn fetch_messages(&self, channel: &str, before: Option<&str>) -> Result<BulkMessageResponse, reqwest::Error> {
+        let before = before.map(|id| format!("&before={id}")).unwrap_or_default();
+
+        self.request::<(), _>(Method::GET, format!("/channels/{channel}/messages?limit={HISTORY_PAGE_SIZE}&include_users=true{before}"), None).await
+    }
+
+    pub async fn edit_message(&self, channel: &str, message: &str, content: &str) -> Result<Message, reqwest::Error> {
+        self.request(Method::PATCH, format!("/channels/{channel}/messages/{message}"), Some(&serde_json::json!({ "content": content }))).await
+    }
+
+    pub async fn delete_message(&self, channel: &str, message: &str) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::DELETE, format!("/channels/{channel}/messages/{message}"), None).await
+    }
+
+    /// `emoji` is either a unicode emoji or the id of a custom emoji.
+    pub async fn react(&self, channel: &str, message: &str, emoji: &str) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::PUT, format!("/channels/{channel}/messages/{message}/reactions/{emoji}"), None).await
+    }
+
+    pub async fn unreact(&self, channel: &str, message: &str, emoji: &str) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::DELETE, format!("/channels/{channel}/messages/{message}/reactions/{emoji}"), None).await
+    }
+
+    pub async fn log_out(&self) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::POST, "/auth/session/logout", None).await
+    }
+
+    pub async fn fetch_unreads(&self) -> Result<Vec<ChannelUnread>, reqwest::Error> {
+        self.request::<(), _>(Method::GET, "/sync/unreads", None).await
+    }
+
+    pub async fn ack_message(&self, channel: &str, message: &str) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::PUT, format!("/channels/{channel}/ack/{message}"), None).await
+    }
+
+    pub async fn fetch_user(&self, user: &str) -> Result<User, reqwest::Error> {
+        self.request::<(), _>(Method::GET, format!("/users/{user}"), None).await
+    }
+
+    pub async fn fetch_profile(&self, user: &str) -> Result<UserProfile, reqwest::Error> {
+        self.request::<(), _>(Method::GET, format!("/users/{user}/profile"), None).await
+    }
+
+    pub async fn fetch_member(&self, server: &str, user: &str) -> Result<Member, reqwest::Error> {
+        self.request::<(), _>(Method::GET, format!("/servers/{server}/members/{user}"), None).await
+    }
+
+    /// Every member of the server along with their users, this can be very large.
+    pub async fn fetch_members(&self, server: &str) -> Result<AllMemberResponse, reqwest::Error> {
+        self.request::<(), _>(Method::GET, format!("/servers/{server}/members"), None).await
+    }
+
+    pub async fn edit_member(&self, server: &str, user: &str, body: &EditMember) -> Result<Member, reqwest::Error> {
+        self.request(Method::PATCH, format!("/servers/{server}/members/{user}"), Some(body)).await
+    }
+
+    pub async fn kick_member(&self, server: &str, user: &str) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::DELETE, format!("/servers/{server}/members/{user}"), None).await
+    }
+
+    pub async fn ban_member(&self, server: &str, user: &str, reason: Option<&str>) -> Result<(), reqwest::Error> {
+        let body = serde_json::json!({ "reason": reason });
+
+        self.request_empty(Method::PUT, format!("/servers/{server}/bans/{user}"), Some(&body)).await
+    }
+
+    /// Only messages younger than a week can be bulk deleted.
+    pub async fn delete_messages(&self, channel: &str, ids: &[String]) -> Result<(), reqwest::Error> {
+        let body = serde_json::json!({ "ids": ids });
+
+        self.request_empty(Method::DELETE, format!("/channels/{channel}/messages/bulk"), Some(&body)).await
+    }
+
+    pub async fn create_role(&self, server: &str, name: &str) -> Result<NewRole, reqwest::Error> {
+        self.request(Method::POST, format!("/servers/{server}/roles"), Some(&serde_json::json!({ "name": name }))).await
+    }
+
+    pub async fn edit_role(&self, server: &str, role: &str, body: &EditRole) -> Result<Role, reqwest::Error> {
+        self.request(Method::PATCH, format!("/servers/{server}/roles/{role}"), Some(body)).await
+    }
+
+    pub async fn set_role_permissions(&self, server: &str, role: &str, allow: u64, deny: u64) -> Result<(), reqwest::Error> {
+        let body = serde_json::json!({ "permissions": { "allow": allow, "deny": deny } });
+
+        self.request_empty(Method::PUT, format!("/servers/{server}/permissions/{role}"), Some(&body)).await
+    }
+
+    pub async fn delete_role(&self, server: &str, role: &str) -> Result<(), reqwest::Error> {
+        self.request_empty::<()>(Method::DELETE, format!("/servers/{server}/roles/{role}"), None).await
+    }
 }
\ No newline at end of file
@@ -0,0 +1,277 @@
+use std::{collections::HashSet, io::Cursor, num::NonZeroUsize, sync::mpsc::{channel, Receiver, Sender}};
+
+use glow::HasContext;
+use image::{codecs::{gif::GifDecoder, webp::WebPDecoder}, AnimationDecoder, ImageFormat, RgbaImage};
+use imgui::TextureId;
+use lru::LruCache;
+
+use crate::notifications::{Notification, Notifier};
+
+/// Browsers treat tiny frame delays as 100ms, do the same so broken gifs don't spin.
+const MIN_FRAME_DELAY: f32 = 0.02;
+const DEFAULT_FRAME_DELAY: f32 = 0.1;
+
+pub struct Frame {
+    pub id: TextureId,
+    native: glow::NativeTexture,
+    delay: f32,
+}
+
+pub struct Texture {
+    frames: Vec<Frame>,
+    current: usize,
+    elapsed: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Texture {
+    /// The frame that should currently be shown.
+    pub fn id(&self) -> TextureId {
+        self.frames[self.current].id
+    }
+
+    pub fn first_id(&self) -> TextureId {
+        self.frames[0].id
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    fn advance(&mut self, delta: f32) {
+        if !self.is_animated() {
+            return
+        };
+
+        self.elapsed += delta;
+
+        while self.elapsed >= self.frames[self.current].delay {
+            self.elapsed -= self.frames[self.current].delay;
+            self.current = (self.current + 1) % self.frames.len();
+        }
+    }
+
+    unsafe fn delete(self, gl: &glow::Context) {
+        for frame in self.frames {
+            gl.delete_texture(frame.native);
+        }
+    }
+}
+
+struct DecodedImage {
+    key: String,
+    result: Result<Vec<(RgbaImage, f32)>, String>,
+}
+
+/// Downloads and decodes images on the tokio runtime and keeps the resulting
+/// textures in a bounded LRU, textures are only created and deleted on the ui thread.
+pub struct ImageCache {
+    client: reqwest::Client,
+    textures: LruCache<String, Texture>,
+    loading: HashSet<String>,
+    failed: HashSet<String>,
+    playing: HashSet<String>,
+    sender: Sender<DecodedImage>,
+    receiver: Receiver<DecodedImage>,
+    notifier: Notifier,
+}
+
+impl ImageCache {
+    pub fn new(capacity: usize, notifier: Notifier) -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            client: reqwest::Client::new(),
+            textures: LruCache::new(NonZeroUsize::new(capacity).unwrap()),
+            loading: HashSet::new(),
+            failed: HashSet::new(),
+            playing: HashSet::new(),
+            sender,
+            receiver,
+            notifier,
+        }
+    }
+
+    /// Returns the texture for `key` if it is loaded, otherwise starts loading it from `url`.
+    ///
+    /// When `max_side` is given the image is downscaled to fit inside a square of that size.
+    pub fn get(&mut self, key: &str, url: &str, max_side: Option<u32>) -> Option<&Texture> {
+        if self.textures.contains(key) {
+            return self.textures.get(key)
+        };
+
+        if !self.loading.contains(key) && !self.failed.contains(key) {
+            self.loading.insert(key.to_string());
+
+            tokio::spawn({
+                let client = self.client.clone();
+                let sender = self.sender.clone();
+                let key = key.to_string();
+                let url = url.to_string();
+
+                async move {
+                    let result = match download(&client, &url).await {
+                        Ok(bytes) => tokio::task::spawn_blocking(move || decode(&bytes, max_side))
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|r| r),
+                        Err(e) => Err(e.to_string())
+                    };
+
+                    let _ = sender.send(DecodedImage { key, result });
+                }
+            });
+        };
+
+        None
+    }
+
+    pub fn is_failed(&self, key: &str) -> bool {
+        self.failed.contains(key)
+    }
+
+    /// Marks an animated image as visible this frame so it keeps playing on the next one.
+    pub fn play(&mut self, key: &str) {
+        if self.textures.peek(key).is_some_and(Texture::is_animated) {
+            self.playing.insert(key.to_string());
+        }
+    }
+
+    /// Advances every animation that was visible last frame, anything not drawn stays paused.
+    pub fn advance(&mut self, delta: f32) {
+        for key in self.playing.drain() {
+            if let Some(texture) = self.textures.peek_mut(&key) {
+                texture.advance(delta);
+            }
+        }
+    }
+
+    /// Deletes every texture, call this before dropping the cache as textures can only be deleted with the gl context.
+    pub fn destroy(&mut self, gl: &glow::Context) {
+        while let Some((_, texture)) = self.textures.pop_lru() {
+            unsafe { texture.delete(gl) };
+        }
+
+        self.playing.clear();
+    }
+
+    /// Uploads every image decoded since the last frame, must be called on the thread owning the gl context.
+    pub fn upload_pending(&mut self, gl: &glow::Context) {
+        while let Ok(DecodedImage { key, result }) = self.receiver.try_recv() {
+            self.loading.remove(&key);
+
+            match result.and_then(|frames| unsafe { upload(gl, frames) }) {
+                Ok(texture) => {
+                    if let Some((_, evicted)) = self.textures.push(key, texture) {
+                        unsafe { evicted.delete(gl) };
+                    }
+                },
+                Err(e) => {
+                    self.notifier.notify(Notification::problem(format!("Couldn't load image {key}"), e));
+                    self.failed.insert(key);
+                }
+            }
+        }
+    }
+}
+
+pub async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, reqwest::Error> {
+    let resp = client.get(url).send().await?.error_for_status()?;
+
+    Ok(resp.bytes().await?.to_vec())
+}
+
+fn resize(image: RgbaImage, max_side: Option<u32>) -> RgbaImage {
+    match max_side {
+        Some(side) if image.width() > side || image.height() > side => {
+            let scale = (side as f32 / image.width() as f32).min(side as f32 / image.height() as f32);
+            let width = ((image.width() as f32 * scale) as u32).max(1);
+            let height = ((image.height() as f32 * scale) as u32).max(1);
+
+            image::imageops::thumbnail(&image, width, height)
+        },
+        _ => image
+    }
+}
+
+fn decode_frames<'a>(decoder: impl AnimationDecoder<'a>, max_side: Option<u32>) -> Result<Vec<(RgbaImage, f32)>, String> {
+    let frames = decoder.into_frames().collect_frames().map_err(|e| e.to_string())?;
+
+    Ok(frames.into_iter().map(|frame| {
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = numer as f32 / denom.max(1) as f32 / 1000.0;
+        let delay = if delay < MIN_FRAME_DELAY { DEFAULT_FRAME_DELAY } else { delay };
+
+        (resize(frame.into_buffer(), max_side), delay)
+    }).collect())
+}
+
+fn decode(bytes: &[u8], max_side: Option<u32>) -> Result<Vec<(RgbaImage, f32)>, String> {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Gif) => {
+            let decoder = GifDecoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+
+            return decode_frames(decoder, max_side)
+        },
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+
+            if decoder.has_animation() {
+                return decode_frames(decoder, max_side)
+            }
+        },
+        _ => {}
+    };
+
+    let image = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+
+    Ok(vec![(resize(image.into_rgba8(), max_side), 0.0)])
+}
+
+unsafe fn upload_frame(gl: &glow::Context, image: &RgbaImage) -> Result<glow::NativeTexture, String> {
+    let native = gl.create_texture()?;
+
+    gl.bind_texture(glow::TEXTURE_2D, Some(native));
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+    gl.tex_image_2d(
+        glow::TEXTURE_2D,
+        0,
+        glow::RGBA as i32,
+        image.width() as i32,
+        image.height() as i32,
+        0,
+        glow::RGBA,
+        glow::UNSIGNED_BYTE,
+        Some(image.as_raw()),
+    );
+    gl.bind_texture(glow::TEXTURE_2D, None);
+
+    Ok(native)
+}
+
+unsafe fn upload(gl: &glow::Context, frames: Vec<(RgbaImage, f32)>) -> Result<Texture, String> {
+    let (width, height) = frames.first()
+        .map(|(image, _)| image.dimensions())
+        .ok_or("Image has no frames")?;
+
+    let mut uploaded = Vec::with_capacity(frames.len());
+
+    for (image, delay) in &frames {
+        match upload_frame(gl, image) {
+            Ok(native) => uploaded.push(Frame { id: TextureId::new(native.0.get() as usize), native, delay: *delay }),
+            Err(e) => {
+                for frame in uploaded {
+                    gl.delete_texture(frame.native);
+                }
+
+                return Err(e)
+            }
+        }
+    }
+
+    Ok(Texture { frames: uploaded, current: 0, elapsed: 0.0, width, height })
+}
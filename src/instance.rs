@@ -0,0 +1,181 @@
+use std::{env, time::Duration};
+
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::AuthClient, http::RevoltConfig, storage};
+
+pub const DEFAULT_INSTANCE: &str = "https://revolt.chat/api";
+const CONFIG_FILE: &str = "config.json";
+const ENV_VAR: &str = "REVOLT_INSTANCE";
+const MAX_RECENT: usize = 5;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client for requests made before there's a session, which give up rather than leave a screen waiting forever.
+pub fn client() -> Client {
+    Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// A Revolt API along with the configuration it reported, every other URL we use comes from that configuration.
+#[derive(Clone)]
+pub struct Instance {
+    pub base_url: String,
+    pub config: RevoltConfig,
+    pub auth: AuthClient,
+}
+
+/// User editable settings in `config.json`, we only ever write back the recent instances.
+#[derive(Serialize, Deserialize, Default)]
+pub struct InstanceSettings {
+    /// Instance to use when none is given on the command line or in the environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default)]
+    pub recent_instances: Vec<String>,
+}
+
+impl InstanceSettings {
+    pub fn load() -> Result<Self, String> {
+        storage::load(CONFIG_FILE).map(Option::unwrap_or_default)
+    }
+
+    /// Moves `base_url` to the top of the recent instances and saves, leaving the file alone if it couldn't be read.
+    pub fn remember(base_url: &str) -> Result<(), String> {
+        let mut settings = Self::load()?;
+
+        settings.recent_instances.retain(|recent| recent != base_url);
+        settings.recent_instances.insert(0, base_url.to_string());
+        settings.recent_instances.truncate(MAX_RECENT);
+
+        storage::save_private(CONFIG_FILE, &settings).map_err(|e| e.to_string())
+    }
+}
+
+/// Picks the instance to start with: `--instance <url>`, then `REVOLT_INSTANCE`, then `config.json`, then whichever
+/// was used last.
+pub fn resolve(settings: &InstanceSettings) -> String {
+    resolve_from(env::args().skip(1), env::var(ENV_VAR).ok(), settings)
+}
+
+fn resolve_from(mut args: impl Iterator<Item = String>, from_env: Option<String>, settings: &InstanceSettings) -> String {
+    let mut from_args = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--instance" {
+            from_args = args.next();
+        } else if let Some(url) = arg.strip_prefix("--instance=") {
+            from_args = Some(url.to_string());
+        };
+    }
+
+    from_args
+        .or_else(|| from_env.filter(|url| !url.trim().is_empty()))
+        .or_else(|| settings.instance.clone())
+        .or_else(|| settings.recent_instances.first().cloned())
+        .unwrap_or_else(|| DEFAULT_INSTANCE.to_string())
+}
+
+/// Tidies up a typed in URL, assuming https when no scheme is given.
+pub fn normalise(input: &str) -> Result<String, String> {
+    let input = input.trim().trim_end_matches('/');
+
+    if input.is_empty() {
+        return Err("Enter the URL of the instance's API".to_string())
+    };
+
+    let with_scheme = if input.contains("://") { input.to_string() } else { format!("https://{input}") };
+    let url = Url::parse(&with_scheme).map_err(|_| format!("{input} isn't a valid URL"))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{input} needs to be an http or https URL"))
+    };
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Fetches the root configuration, explaining what went wrong in terms someone setting up an instance can act on.
+pub async fn connect(input: &str) -> Result<Instance, String> {
+    let base_url = normalise(input)?;
+
+    let response = client().get(&base_url)
+        .send().await
+        .map_err(|e| format!("Couldn't reach {base_url}: {e}"))?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(format!("{base_url} returned {status}, make sure this is the API URL rather than the web app"))
+    };
+
+    let config = response.json::<RevoltConfig>().await
+        .map_err(|_| format!("{base_url} doesn't look like a Revolt API, make sure this is the API URL (it often ends in /api)"))?;
+
+    if Url::parse(&config.ws).map_or(true, |url| !matches!(url.scheme(), "ws" | "wss")) {
+        return Err(format!("{base_url} reported an invalid WebSocket URL \"{}\"", config.ws))
+    };
+
+    // Self-hosted instances can turn these off, in which case they report an empty URL.
+    for (name, feature) in [("Autumn", &config.features.autumn), ("January", &config.features.january)] {
+        if feature.enabled && Url::parse(&feature.url).is_err() {
+            return Err(format!("{base_url} reported an invalid {name} URL \"{}\"", feature.url))
+        }
+    }
+
+    Ok(Instance { auth: AuthClient::new(base_url.clone()), base_url, config })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    fn settings(instance: Option<&str>, recent: &[&str]) -> InstanceSettings {
+        InstanceSettings {
+            instance: instance.map(str::to_string),
+            recent_instances: recent.iter().map(|url| url.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn normalise_tidies_urls() {
+        assert_eq!(normalise("revolt.chat/api").unwrap(), "https://revolt.chat/api");
+        assert_eq!(normalise("  https://revolt.chat/api/ ").unwrap(), "https://revolt.chat/api");
+        assert_eq!(normalise("http://localhost:14702").unwrap(), "http://localhost:14702");
+        assert_eq!(normalise("HTTPS://Example.com/api").unwrap(), "https://example.com/api");
+    }
+
+    #[test]
+    fn normalise_rejects_bad_urls() {
+        assert!(normalise("").is_err());
+        assert!(normalise("   /").is_err());
+        assert!(normalise("ftp://example.com").is_err());
+        assert!(normalise("https://exa mple.com").is_err());
+    }
+
+    #[test]
+    fn resolve_order() {
+        let saved = settings(Some("https://configured.example"), &["https://recent.example"]);
+        let from_env = Some("https://env.example".to_string());
+
+        assert_eq!(resolve_from(args(&["--instance", "https://args.example"]), from_env.clone(), &saved), "https://args.example");
+        assert_eq!(resolve_from(args(&["--instance=https://args.example"]), from_env.clone(), &saved), "https://args.example");
+        assert_eq!(resolve_from(args(&[]), from_env, &saved), "https://env.example");
+        assert_eq!(resolve_from(args(&[]), Some("  ".to_string()), &saved), "https://configured.example");
+        assert_eq!(resolve_from(args(&[]), None, &settings(None, &["https://recent.example"])), "https://recent.example");
+        assert_eq!(resolve_from(args(&[]), None, &settings(None, &[])), DEFAULT_INSTANCE);
+    }
+
+    #[test]
+    fn resolve_ignores_missing_argument() {
+        assert_eq!(resolve_from(args(&["--instance"]), None, &settings(None, &[])), DEFAULT_INSTANCE);
+        assert_eq!(resolve_from(args(&["--other", "value"]), None, &settings(None, &[])), DEFAULT_INSTANCE);
+    }
+}
@@ -1,14 +1,29 @@
-use std::env::args;
-
-use http::RevoltConfig;
-use imgui::Condition;
-use state::GlobalState;
+use actions::AccountId;
+use app::{App, LoginOutcome, OnboardingOutcome, Screen};
+use imgui::{Condition, Ui};
+use state::{DisplayMode, GlobalState, UnreadSummary};
+use time::ClockFormat;
 
 mod setup;
 mod components;
 mod state;
 mod websocket;
 mod http;
+mod images;
+mod colour;
+mod time;
+mod markup;
+mod names;
+mod permissions;
+mod fetch;
+mod actions;
+mod notifications;
+mod app;
+mod auth;
+mod storage;
+mod instance;
+#[cfg(test)]
+mod mock_server;
 use revolt_models::v0;
 // fn full(ui: &mut RevoltUi, messages: &mut Vec<Message>, current_channel: &mut Option<String>, current_hover: &mut Option<String>, selected_member: &mut Option<String>, current_message: &mut String) {
 //     ui.window("Revolt")
@@ -42,13 +57,7 @@ use revolt_models::v0;
 
 // }
 
-static BASE_URL: &'static str = "https://revolt.chat/api";
-
 fn main() {
-    let token = args().nth(1).expect("No token given");
-
-    println!("Logging in with token {token}");
-
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
@@ -56,46 +65,168 @@ fn main() {
 
     let _t = rt.enter();
 
-    let api_config = rt.block_on(async {
-        reqwest::get(BASE_URL).await.unwrap().json::<RevoltConfig>().await.unwrap()
-    });
-
     setup::init(
         "Revolt",
-        {
-            let api_config = api_config.clone();
-            let token = token.clone();
-
-            || {
-                GlobalState::new(BASE_URL.to_string(), api_config, token)
+        App::new,
+        app::update_app,
+        move |_running, ui, gl, focused, app| {
+            app.update(gl);
+
+            match &mut app.screen {
+                Screen::Resuming => components::resuming(ui),
+                Screen::Login(screen) => {
+                    match components::login(ui, screen, !app.accounts.is_empty()) {
+                        Some(LoginOutcome::LoggedIn(instance, credentials, remember)) => app.log_in(instance, credentials, remember),
+                        Some(LoginOutcome::Cancelled) => app.cancel_login(),
+                        None => {}
+                    }
+                },
+                Screen::Onboarding(screen) => {
+                    match components::onboarding(ui, screen) {
+                        Some(OnboardingOutcome::Done) => app.finish_onboarding(),
+                        Some(OnboardingOutcome::Cancelled) => app.cancel_onboarding(),
+                        None => {}
+                    }
+                },
+                Screen::Client => {
+                    let accounts = app.accounts.iter()
+                        .map(|account| (account.id, account.name(), account.unread_summary()))
+                        .collect::<Vec<_>>();
+                    let active = app.active;
+
+                    let Some(account) = app.active_account() else { return };
+
+                    match client(ui, gl, focused, &mut account.state, &accounts, active) {
+                        Some(AccountAction::Switch(id)) => app.switch_to(id),
+                        Some(AccountAction::Add) => app.add_account(),
+                        Some(AccountAction::LogOut) => app.log_out(gl),
+                        None => {}
+                    }
+                }
             }
-        },
-        move |sender | async {
-            websocket::run(sender, token, api_config).await;
-        },
-        state::update_state,
-        move |_running, ui, state| {
-        ui.window("Channel")
-            .menu_bar(true)
-            .size([400.0, 600.0], Condition::FirstUseEver)
-            .resizable(true)
-            .build(|| components::channel(ui, state));
-
-        ui.window("Message Box")
-            .size([0.0, 0.0], Condition::FirstUseEver)
-            .resizable(true)
-            .build(|| components::message_box(ui, state));
-
-        ui.window("Server List")
-            .menu_bar(true)
-            .size([400.0, 600.0], Condition::FirstUseEver)
-            .resizable(true)
-            .build(|| components::server_list(ui, state));
-
-        ui.window("Member List")
-            .menu_bar(true)
-            .size([400.0, 600.0], Condition::FirstUseEver)
-            .resizable(true)
-            .build(|| components::members(ui, state));
+        });
+}
+
+enum AccountAction {
+    Switch(AccountId),
+    Add,
+    LogOut,
+}
+
+fn unread_label(summary: UnreadSummary) -> String {
+    match summary {
+        UnreadSummary { mentions: 1.., .. } => format!(" ({} mentions)", summary.mentions),
+        UnreadSummary { unread: true, .. } => " (unread)".to_string(),
+        _ => String::new()
+    }
+}
+
+/// Switcher listing every signed in account with its unread state.
+fn account_menu(ui: &Ui, accounts: &[(AccountId, String, UnreadSummary)], active: AccountId) -> Option<AccountAction> {
+    let mut action = None;
+
+    let other_mentions = accounts.iter()
+        .filter(|(id, _, _)| *id != active)
+        .map(|(_, _, summary)| summary.mentions)
+        .sum::<usize>();
+
+    let title = if other_mentions > 0 { format!("Accounts ({other_mentions})###Accounts") } else { "Accounts###Accounts".to_string() };
+
+    ui.menu(title, || {
+        for (id, name, summary) in accounts {
+            let label = format!("{name}{}##{id}", unread_label(*summary));
+
+            if ui.menu_item_config(label).selected(*id == active).build() && *id != active {
+                action = Some(AccountAction::Switch(*id));
+            };
+        }
+
+        ui.separator();
+
+        if ui.menu_item("Add Account...") {
+            action = Some(AccountAction::Add);
+        };
+
+        if ui.menu_item("Log Out") {
+            action = Some(AccountAction::LogOut);
+        };
     });
+
+    action
+}
+
+/// Draws the active account's client, returns what to do with the accounts if the user picked something from the
+/// account menu.
+fn client(ui: &Ui, gl: &glow::Context, focused: bool, state: &mut GlobalState, accounts: &[(AccountId, String, UnreadSummary)], active: AccountId) -> Option<AccountAction> {
+    let mut account_action = None;
+
+    state.images.upload_pending(gl);
+
+    // Animations pause while the app is in the background.
+    if focused {
+        state.images.advance(ui.io().delta_time);
+    };
+
+    ui.main_menu_bar(|| {
+        account_action = account_menu(ui, accounts, active);
+
+        ui.menu("Settings", || {
+            ui.checkbox("Animate on hover only", &mut state.settings.animate_on_hover_only);
+
+            ui.separator();
+            ui.text_disabled("Message Display");
+            ui.radio_button("Cozy", &mut state.settings.display_mode, DisplayMode::Cozy);
+            ui.radio_button("Compact", &mut state.settings.display_mode, DisplayMode::Compact);
+
+            ui.separator();
+            ui.text_disabled("Time Format");
+            ui.radio_button("24 Hour", &mut state.settings.clock_format, ClockFormat::TwentyFourHour);
+            ui.radio_button("12 Hour", &mut state.settings.clock_format, ClockFormat::TwelveHour);
+        });
+
+        ui.menu("View", || {
+            let label = format!("Problems ({})", state.notifications.problems.len());
+
+            ui.menu_item_config(label).build_with_ref(&mut state.notifications.problems_open);
+
+            if state.is_bot() {
+                ui.menu_item_config("Bot Overview").build_with_ref(&mut state.bot_overview_open);
+            };
+        });
+    });
+
+    ui.window("Channel")
+        .menu_bar(true)
+        .size([400.0, 600.0], Condition::FirstUseEver)
+        .resizable(true)
+        .build(|| components::channel(ui, state));
+
+    ui.window("Message Box")
+        .size([0.0, 0.0], Condition::FirstUseEver)
+        .resizable(true)
+        .build(|| components::message_box(ui, state));
+
+    ui.window("Server List")
+        .menu_bar(true)
+        .size([400.0, 600.0], Condition::FirstUseEver)
+        .resizable(true)
+        .build(|| components::server_list(ui, state));
+
+    ui.window("Member List")
+        .menu_bar(true)
+        .size([400.0, 600.0], Condition::FirstUseEver)
+        .resizable(true)
+        .build(|| components::members(ui, state));
+
+    components::attachment_viewer(ui, state);
+    components::role_editor(ui, state);
+    components::moderation_dialog(ui, state);
+    components::bot_overview(ui, state);
+    components::problems(ui, state);
+    components::toasts(ui, state);
+    components::status_bar(ui, state);
+
+    state.end_frame();
+
+    account_action
 }
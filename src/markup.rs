@@ -0,0 +1,90 @@
+use crate::{names::{display_name, name_colour, Author}, state::GlobalState, time};
+
+/// Timestamps outside years 0 to 9999 are left as they are, the date maths isn't meant for anything further out.
+const MIN_UNIX: i64 = -62_167_219_200;
+const MAX_UNIX: i64 = 253_402_300_799;
+
+/// A run of message text, mentions are drawn in the colour of the user's name.
+pub struct Span {
+    pub text: String,
+    pub colour: Option<[f32; 4]>,
+}
+
+/// Splits message content into spans, replacing the `<t:unix:style>` timestamp and `<@user>` mention tokens with
+/// readable text.
+pub fn content_spans(state: &GlobalState, server: Option<&str>, content: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut text = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let token = rest.find('>').and_then(|end| {
+            let inner = &rest[1..end];
+
+            let span = if let Some(timestamp) = inner.strip_prefix("t:") {
+                timestamp_token(state, timestamp).map(|text| Span { text, colour: None })
+            } else if let Some(user_id) = inner.strip_prefix('@').filter(|id| ulid::Ulid::from_string(id).is_ok()) {
+                Some(Span {
+                    text: format!("@{}", display_name(state, server, Author::Id(user_id))),
+                    colour: name_colour(state, server, Author::Id(user_id)),
+                })
+            } else {
+                None
+            };
+
+            span.map(|span| (end, span))
+        });
+
+        match token {
+            Some((end, span)) if span.colour.is_some() => {
+                spans.push(Span { text: std::mem::take(&mut text), colour: None });
+                spans.push(span);
+                rest = &rest[end + 1..];
+            },
+            Some((end, span)) => {
+                text.push_str(&span.text);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                text.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    text.push_str(rest);
+    spans.push(Span { text, colour: None });
+    spans.retain(|span| !span.text.is_empty());
+
+    spans
+}
+
+/// Message content as plain text, for places which can't show colours.
+pub fn render_content(state: &GlobalState, server: Option<&str>, content: &str) -> String {
+    content_spans(state, server, content).into_iter().map(|span| span.text).collect()
+}
+
+fn timestamp_token(state: &GlobalState, token: &str) -> Option<String> {
+    let (unix, style) = match token.split_once(':') {
+        Some((unix, style)) => (unix, style),
+        None => (token, "f")
+    };
+
+    let timestamp_ms = unix.parse::<i64>().ok().filter(|unix| (MIN_UNIX..=MAX_UNIX).contains(unix))? * 1000;
+    let datetime = state.clock.from_millis(timestamp_ms);
+    let format = state.settings.clock_format;
+
+    Some(match style {
+        "t" => state.clock.time(&datetime, format),
+        "T" => state.clock.time_with_seconds(&datetime, format),
+        "d" => state.clock.short_date(&datetime),
+        "D" => state.clock.long_date(&datetime),
+        "f" => format!("{} {}", state.clock.long_date(&datetime), state.clock.time(&datetime, format)),
+        "F" => state.clock.full(timestamp_ms, format),
+        "R" => time::from_now(timestamp_ms),
+        _ => return None
+    })
+}
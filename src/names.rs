@@ -0,0 +1,80 @@
+use revolt_models::v0::{Masquerade, Message, User};
+
+use crate::{colour::parse_css_colour, state::GlobalState};
+
+/// Anything a name can be shown for, messages can override the author's name through masquerades.
+#[derive(Clone, Copy)]
+pub enum Author<'a> {
+    Message(&'a Message),
+    User(&'a User),
+    Id(&'a str),
+}
+
+impl<'a> From<&'a Message> for Author<'a> {
+    fn from(message: &'a Message) -> Self {
+        Author::Message(message)
+    }
+}
+
+impl<'a> From<&'a User> for Author<'a> {
+    fn from(user: &'a User) -> Self {
+        Author::User(user)
+    }
+}
+
+impl<'a> Author<'a> {
+    fn user_id(&self) -> &'a str {
+        match self {
+            Author::Message(message) => &message.author,
+            Author::User(user) => &user.id,
+            Author::Id(id) => id,
+        }
+    }
+
+    fn masquerade(&self) -> Option<&'a Masquerade> {
+        match self {
+            Author::Message(message) => message.masquerade.as_ref(),
+            _ => None
+        }
+    }
+}
+
+/// Resolves the name to show for an author: masquerade, then server nickname, then display name, then username.
+pub fn display_name<'a>(state: &GlobalState, server: Option<&str>, author: impl Into<Author<'a>>) -> String {
+    let author = author.into();
+    let user_id = author.user_id();
+
+    let user = match author {
+        Author::User(user) => Some(user),
+        _ => state.users.get(user_id)
+    };
+
+    let member = server
+        .and_then(|server| state.members.get(server))
+        .and_then(|members| members.get(user_id));
+
+    author.masquerade()
+        .and_then(|masq| masq.name.clone())
+        .or_else(|| member.and_then(|m| m.nickname.clone()))
+        .or_else(|| user.and_then(|u| u.display_name.clone()))
+        .or_else(|| user.map(|u| u.username.clone()))
+        .unwrap_or_else(|| "Unknown User".to_string())
+}
+
+/// Resolves the colour of an author's name: masquerade colour, then their highest ranked coloured role.
+pub fn name_colour<'a>(state: &GlobalState, server: Option<&str>, author: impl Into<Author<'a>>) -> Option<[f32; 4]> {
+    let author = author.into();
+
+    if let Some(colour) = author.masquerade().and_then(|masq| masq.colour.as_deref()) {
+        return parse_css_colour(colour)
+    };
+
+    let server = state.servers.get(server?)?;
+    let member = state.members.get(&server.id)?.get(author.user_id())?;
+
+    member.roles.iter()
+        .filter_map(|id| server.roles.get(id))
+        .filter_map(|role| Some((role.rank, parse_css_colour(role.colour.as_deref()?)?)))
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, colour)| colour)
+}
@@ -0,0 +1,123 @@
+use std::{collections::VecDeque, sync::mpsc::{channel, Receiver, Sender}, time::{Duration, Instant}};
+
+use crate::time::Clock;
+
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const MAX_TOASTS: usize = 5;
+/// Older problems are dropped so a misbehaving connection can't grow the log forever.
+const MAX_PROBLEMS: usize = 500;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Success,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub level: Level,
+    pub message: String,
+    /// Errors with details are kept in the problems log.
+    pub details: Option<String>,
+    /// Whether to pop up a toast, background problems like broken images only go to the log.
+    pub toast: bool,
+}
+
+impl Notification {
+    pub fn success(message: impl Into<String>) -> Self {
+        Self { level: Level::Success, message: message.into(), details: None, toast: true }
+    }
+
+    pub fn error(message: impl Into<String>, details: impl Into<String>) -> Self {
+        Self { level: Level::Error, message: message.into(), details: Some(details.into()), toast: true }
+    }
+
+    pub fn problem(message: impl Into<String>, details: impl Into<String>) -> Self {
+        Self { toast: false, ..Self::error(message, details) }
+    }
+}
+
+pub struct Toast {
+    pub notification: Notification,
+    shown_at: Instant,
+}
+
+pub struct Problem {
+    pub at_ms: i64,
+    pub message: String,
+    pub details: String,
+}
+
+/// Lets background tasks push notifications, they show up on the next frame.
+#[derive(Clone)]
+pub struct Notifier(Sender<Notification>);
+
+impl Notifier {
+    /// A notifier which isn't tied to any [`Notifications`], for things happening outside an account.
+    pub fn channel() -> (Self, Receiver<Notification>) {
+        let (sender, receiver) = channel();
+
+        (Self(sender), receiver)
+    }
+
+    pub fn notify(&self, notification: Notification) {
+        self.0.send(notification).ok();
+    }
+}
+
+pub struct Notifications {
+    pub toasts: VecDeque<Toast>,
+    pub problems: VecDeque<Problem>,
+    pub problems_open: bool,
+    sender: Sender<Notification>,
+    receiver: Receiver<Notification>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            toasts: VecDeque::new(),
+            problems: VecDeque::new(),
+            problems_open: false,
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn notifier(&self) -> Notifier {
+        Notifier(self.sender.clone())
+    }
+
+    pub fn push(&mut self, notification: Notification) {
+        if let Some(details) = &notification.details {
+            self.problems.push_back(Problem {
+                at_ms: Clock::now_ms(),
+                message: notification.message.clone(),
+                details: details.clone(),
+            });
+
+            if self.problems.len() > MAX_PROBLEMS {
+                self.problems.pop_front();
+            };
+        };
+
+        if notification.toast {
+            self.toasts.push_back(Toast { notification, shown_at: Instant::now() });
+
+            if self.toasts.len() > MAX_TOASTS {
+                self.toasts.pop_front();
+            };
+        };
+    }
+
+    /// Picks up notifications from background tasks and drops expired toasts, called once per frame.
+    pub fn update(&mut self) {
+        while let Ok(notification) = self.receiver.try_recv() {
+            self.push(notification);
+        }
+
+        self.toasts.retain(|toast| toast.shown_at.elapsed() < TOAST_DURATION);
+    }
+}
@@ -0,0 +1,261 @@
+use std::ops::{BitAnd, BitOr, Not};
+
+use iso8601_timestamp::Timestamp;
+use revolt_models::v0::{Channel, Member, OverrideField, RelationshipStatus};
+
+use crate::state::GlobalState;
+
+/// Revolt's permission bitfield, shared by servers, roles and channels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Permissions(pub u64);
+
+impl Permissions {
+    pub const NONE: Permissions = Permissions(0);
+    pub const ALL: Permissions = Permissions(u64::MAX);
+
+    pub const MANAGE_CHANNEL: Permissions = Permissions(1 << 0);
+    pub const MANAGE_SERVER: Permissions = Permissions(1 << 1);
+    pub const MANAGE_PERMISSIONS: Permissions = Permissions(1 << 2);
+    pub const MANAGE_ROLE: Permissions = Permissions(1 << 3);
+    pub const MANAGE_CUSTOMISATION: Permissions = Permissions(1 << 4);
+    pub const KICK_MEMBERS: Permissions = Permissions(1 << 6);
+    pub const BAN_MEMBERS: Permissions = Permissions(1 << 7);
+    pub const TIMEOUT_MEMBERS: Permissions = Permissions(1 << 8);
+    pub const ASSIGN_ROLES: Permissions = Permissions(1 << 9);
+    pub const CHANGE_NICKNAME: Permissions = Permissions(1 << 10);
+    pub const MANAGE_NICKNAMES: Permissions = Permissions(1 << 11);
+    pub const CHANGE_AVATAR: Permissions = Permissions(1 << 12);
+    pub const REMOVE_AVATARS: Permissions = Permissions(1 << 13);
+    pub const VIEW_CHANNEL: Permissions = Permissions(1 << 20);
+    pub const READ_MESSAGE_HISTORY: Permissions = Permissions(1 << 21);
+    pub const SEND_MESSAGE: Permissions = Permissions(1 << 22);
+    pub const MANAGE_MESSAGES: Permissions = Permissions(1 << 23);
+    pub const MANAGE_WEBHOOKS: Permissions = Permissions(1 << 24);
+    pub const INVITE_OTHERS: Permissions = Permissions(1 << 25);
+    pub const SEND_EMBEDS: Permissions = Permissions(1 << 26);
+    pub const UPLOAD_FILES: Permissions = Permissions(1 << 27);
+    pub const MASQUERADE: Permissions = Permissions(1 << 28);
+    pub const REACT: Permissions = Permissions(1 << 29);
+    pub const CONNECT: Permissions = Permissions(1 << 30);
+    pub const SPEAK: Permissions = Permissions(1 << 31);
+    pub const VIDEO: Permissions = Permissions(1 << 32);
+    pub const MUTE_MEMBERS: Permissions = Permissions(1 << 33);
+    pub const DEAFEN_MEMBERS: Permissions = Permissions(1 << 34);
+    pub const MOVE_MEMBERS: Permissions = Permissions(1 << 35);
+
+    /// Everything except permissions which are reserved for future use.
+    pub const GRANT_ALL_SAFE: Permissions = Permissions(0x000F_FFFF_FFFF_FFFF);
+    pub const VIEW_ONLY: Permissions = Permissions(Self::VIEW_CHANNEL.0 | Self::READ_MESSAGE_HISTORY.0);
+    pub const DEFAULT: Permissions = Permissions(
+        Self::VIEW_ONLY.0 | Self::SEND_MESSAGE.0 | Self::INVITE_OTHERS.0 | Self::SEND_EMBEDS.0
+            | Self::UPLOAD_FILES.0 | Self::CONNECT.0 | Self::SPEAK.0
+    );
+    pub const DEFAULT_DIRECT_MESSAGE: Permissions = Permissions(Self::DEFAULT.0 | Self::MANAGE_CHANNEL.0 | Self::REACT.0);
+    /// What a timed out member keeps.
+    pub const ALLOWED_IN_TIMEOUT: Permissions = Self::VIEW_ONLY;
+
+    /// Every named permission, in the order the role editor lists them.
+    pub const NAMED: &'static [(Permissions, &'static str)] = &[
+        (Self::MANAGE_CHANNEL, "Manage Channels"),
+        (Self::MANAGE_SERVER, "Manage Server"),
+        (Self::MANAGE_PERMISSIONS, "Manage Permissions"),
+        (Self::MANAGE_ROLE, "Manage Roles"),
+        (Self::MANAGE_CUSTOMISATION, "Manage Customisation"),
+        (Self::KICK_MEMBERS, "Kick Members"),
+        (Self::BAN_MEMBERS, "Ban Members"),
+        (Self::TIMEOUT_MEMBERS, "Timeout Members"),
+        (Self::ASSIGN_ROLES, "Assign Roles"),
+        (Self::CHANGE_NICKNAME, "Change Nickname"),
+        (Self::MANAGE_NICKNAMES, "Manage Nicknames"),
+        (Self::CHANGE_AVATAR, "Change Avatar"),
+        (Self::REMOVE_AVATARS, "Remove Avatars"),
+        (Self::VIEW_CHANNEL, "View Channel"),
+        (Self::READ_MESSAGE_HISTORY, "Read Message History"),
+        (Self::SEND_MESSAGE, "Send Messages"),
+        (Self::MANAGE_MESSAGES, "Manage Messages"),
+        (Self::MANAGE_WEBHOOKS, "Manage Webhooks"),
+        (Self::INVITE_OTHERS, "Invite Others"),
+        (Self::SEND_EMBEDS, "Send Embeds"),
+        (Self::UPLOAD_FILES, "Upload Files"),
+        (Self::MASQUERADE, "Masquerade"),
+        (Self::REACT, "React"),
+        (Self::CONNECT, "Connect"),
+        (Self::SPEAK, "Speak"),
+        (Self::VIDEO, "Video"),
+        (Self::MUTE_MEMBERS, "Mute Members"),
+        (Self::DEAFEN_MEMBERS, "Deafen Members"),
+        (Self::MOVE_MEMBERS, "Move Members"),
+    ];
+
+    pub fn has(self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Applies a role or channel override, denies are removed after allows are added.
+    pub fn apply(self, field: &OverrideField) -> Permissions {
+        Permissions((self.0 | field.a as u64) & !(field.d as u64))
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Permissions;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Permissions(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Permissions;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Permissions(self.0 & rhs.0)
+    }
+}
+
+impl Not for Permissions {
+    type Output = Permissions;
+
+    fn not(self) -> Self {
+        Permissions(!self.0)
+    }
+}
+
+pub fn is_timed_out(member: &Member) -> bool {
+    member.timeout.as_ref().is_some_and(|timeout| *timeout > Timestamp::now_utc())
+}
+
+impl GlobalState {
+    /// The current user's permissions in a channel, following the same rules as the server.
+    pub fn permissions(&self, channel_id: &str) -> Permissions {
+        let (Some(channel), Some(user_id)) = (self.channels.get(channel_id), self.user_id.as_deref()) else {
+            return Permissions::NONE
+        };
+
+        match channel {
+            Channel::SavedMessages { user, .. } => {
+                if user == user_id { Permissions::GRANT_ALL_SAFE } else { Permissions::NONE }
+            },
+            Channel::DirectMessage { recipients, .. } => {
+                if !recipients.iter().any(|id| id == user_id) {
+                    return Permissions::NONE
+                };
+
+                let blocked = self.dm_recipient(channel)
+                    .is_some_and(|user| matches!(user.relationship, RelationshipStatus::Blocked | RelationshipStatus::BlockedOther));
+
+                if blocked { Permissions::VIEW_ONLY } else { Permissions::DEFAULT_DIRECT_MESSAGE }
+            },
+            Channel::Group { owner, recipients, permissions, .. } => {
+                if owner == user_id {
+                    Permissions::GRANT_ALL_SAFE
+                } else if recipients.iter().any(|id| id == user_id) {
+                    permissions.map(|p| Permissions(p as u64)).unwrap_or(Permissions::DEFAULT_DIRECT_MESSAGE) | Permissions::VIEW_ONLY
+                } else {
+                    Permissions::NONE
+                }
+            },
+            Channel::TextChannel { server, default_permissions, role_permissions, .. }
+            | Channel::VoiceChannel { server, default_permissions, role_permissions, .. } => {
+                let Some(server) = self.servers.get(server) else { return Permissions::NONE };
+
+                if server.owner == user_id {
+                    return Permissions::GRANT_ALL_SAFE
+                };
+
+                let Some(member) = self.members.get(&server.id).and_then(|members| members.get(user_id)) else {
+                    return Permissions::NONE
+                };
+
+                let mut permissions = self.server_permissions(&server.id);
+
+                if let Some(default_permissions) = default_permissions {
+                    permissions = permissions.apply(default_permissions);
+                };
+
+                let mut roles = member.roles.iter()
+                    .filter_map(|id| Some((server.roles.get(id)?.rank, role_permissions.get(id)?)))
+                    .collect::<Vec<_>>();
+
+                roles.sort_by_key(|(rank, _)| std::cmp::Reverse(*rank));
+
+                for (_, role_override) in roles {
+                    permissions = permissions.apply(role_override);
+                }
+
+                if is_timed_out(member) {
+                    permissions = permissions & Permissions::ALLOWED_IN_TIMEOUT;
+                };
+
+                if permissions.has(Permissions::VIEW_CHANNEL) { permissions } else { Permissions::NONE }
+            }
+        }
+    }
+
+    /// The current user's permissions across a server, before any channel overrides.
+    pub fn server_permissions(&self, server_id: &str) -> Permissions {
+        let (Some(server), Some(user_id)) = (self.servers.get(server_id), self.user_id.as_deref()) else {
+            return Permissions::NONE
+        };
+
+        if server.owner == user_id {
+            return Permissions::ALL
+        };
+
+        let Some(member) = self.members.get(server_id).and_then(|members| members.get(user_id)) else {
+            return Permissions::NONE
+        };
+
+        let mut roles = member.roles.iter()
+            .filter_map(|id| server.roles.get(id))
+            .collect::<Vec<_>>();
+
+        // Lower ranks take priority so they're applied last.
+        roles.sort_by_key(|role| std::cmp::Reverse(role.rank));
+
+        let permissions = roles.into_iter().fold(Permissions(server.default_permissions as u64), |permissions, role| permissions.apply(&role.permissions));
+
+        if is_timed_out(member) {
+            permissions & Permissions::ALLOWED_IN_TIMEOUT
+        } else {
+            permissions
+        }
+    }
+
+    /// Whether we can use a moderation permission on a member, the reason it would be rejected otherwise.
+    pub fn can_moderate(&self, server_id: &str, user_id: &str, permission: Permissions) -> Result<(), &'static str> {
+        let Some(own_id) = self.user_id.as_deref() else { return Err("Not logged in") };
+
+        if own_id == user_id {
+            return Err("You can't do this to yourself")
+        };
+
+        if self.servers.get(server_id).is_some_and(|server| server.owner == user_id) {
+            return Err("The server owner can't be moderated")
+        };
+
+        if !self.server_permissions(server_id).has(permission) {
+            return Err("You don't have permission to do this")
+        };
+
+        if self.member_rank(server_id, own_id) >= self.member_rank(server_id, user_id) {
+            return Err("This member's highest role is not below yours")
+        };
+
+        Ok(())
+    }
+
+    /// The rank of a member's highest role, lower is more powerful. Owners outrank everyone.
+    pub fn member_rank(&self, server_id: &str, user_id: &str) -> i64 {
+        let Some(server) = self.servers.get(server_id) else { return i64::MAX };
+
+        if server.owner == user_id {
+            return i64::MIN
+        };
+
+        self.members.get(server_id)
+            .and_then(|members| members.get(user_id))
+            .and_then(|member| member.roles.iter().filter_map(|id| server.roles.get(id)).map(|role| role.rank).min())
+            .unwrap_or(i64::MAX)
+    }
+}
@@ -1,4 +1,4 @@
-use futures::{Future, StreamExt};
+use futures::StreamExt;
 use glutin::context::{NotCurrentGlContext, PossiblyCurrentGlContext};
 use imgui::{BackendFlags, ClipboardBackend, Condition, FontConfig, FontSource, StyleColor, TableFlags, Ui};
 use copypasta::{ClipboardContext, ClipboardProvider};
@@ -45,19 +45,18 @@ impl ClipboardBackend for ClipboardSupport {
     }
 }
 
-pub fn init<S, E, FState, FBg, FBgF, FUpdate, FUi>(
+/// Opens the window and runs the UI. `create_state` gets the sender for events which are fed to `update_state` on the
+/// UI thread, so it can hand it to whatever background tasks it starts. `run_ui` is told whether any of our windows
+/// has focus.
+pub fn init<S, E, FState, FUpdate, FUi>(
     title: &str,
     create_state: FState,
-    background_task: FBg,
     update_state: FUpdate,
     mut run_ui: FUi
 ) where
-    FState: FnOnce() -> S,
-    FBg: FnOnce(Sender<E>) -> FBgF + Send + Sync + 'static,
-    FBgF: Future + Send + Sync + 'static,
-    <FBgF as Future>::Output: Send + Sync + 'static,
+    FState: FnOnce(Sender<E>) -> S,
     FUpdate: Fn(E, &mut S) -> (),
-    FUi: FnMut(&mut bool, &mut Ui, &mut S) + 'static,
+    FUi: FnMut(&mut bool, &mut Ui, &Context, bool, &mut S) + 'static,
 {
     let mut imgui = create_context();
 
@@ -122,11 +121,11 @@ pub fn init<S, E, FState, FBg, FBgF, FUpdate, FUi>(
 
     let (event_sender, event_receiver) = channel::<E>();
 
-    tokio::spawn(background_task(event_sender));
-
     let mut renderer = Renderer::new(&mut imgui, &window, &glow).unwrap();
     let mut last_frame = Instant::now();
-    let mut state = create_state();
+    let mut state = create_state(event_sender);
+    // Viewports are separate OS windows, moving between them unfocuses one just before focusing the other.
+    let mut focused = true;
 
     event_loop
         .run(move |event, window_target| {
@@ -155,7 +154,7 @@ pub fn init<S, E, FState, FBg, FBgF, FUpdate, FUi>(
                     ui.dockspace_over_main_viewport();
 
                     let mut run = true;
-                    run_ui(&mut run, ui, &mut state);
+                    run_ui(&mut run, ui, &glow, focused, &mut state);
                     if !run {
                         window_target.exit();
                     }
@@ -204,6 +203,12 @@ pub fn init<S, E, FState, FBg, FBgF, FUpdate, FUi>(
                         NonZeroU32::new(new_size.height).unwrap(),
                     );
             }
+                Event::WindowEvent {
+                    event: WindowEvent::Focused(window_focused),
+                    ..
+                } => {
+                    focused = window_focused;
+                }
                 Event::WindowEvent {
                     window_id,
                     event: WindowEvent::CloseRequested,
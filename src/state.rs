@@ -1,45 +1,213 @@
-use std::{any::Any, cell::{Cell, RefCell}, collections::HashMap, sync::{Arc, Mutex}};
+use std::{any::Any, collections::{HashMap, HashSet}, future::Future, sync::{mpsc::{channel, Receiver}, Arc, Mutex}, time::{Duration, Instant}};
 
 use imgui::Ui;
+use tokio::task::JoinHandle;
 
-use revolt_database::events::client::EventV1;
-use revolt_models::v0::{Channel, Member, Message, Server, User};
+use revolt_database::events::client::{EventV1, Ping};
+use iso8601_timestamp::Timestamp;
+use revolt_models::v0::{BulkMessageResponse, Channel, ChannelUnread, FieldsMember, FieldsRole, FieldsUser, File, Member, MemberCompositeKey, Message, OverrideField, RelationshipStatus, Role, Server, User, UserProfile};
 
-use crate::http::{HttpClient, RevoltConfig};
+use crate::{actions::{Action, ActionError, ActionId, Actions, AppEvent, Output}, fetch::{FetchKey, Fetcher}, http::{HttpClient, RevoltConfig, TokenKind, HISTORY_PAGE_SIZE}, images::ImageCache, notifications::{Notification, Notifications}, time::{Clock, ClockFormat}};
 
+/// Contexts which haven't been rendered for this many frames are dropped along with their hooks.
+const CONTEXT_TTL_FRAMES: u64 = 300;
+
+struct Hook {
+    value: Box<dyn Any>,
+    type_name: &'static str,
+}
+
+impl Hook {
+    fn new<R: 'static>(value: R) -> Self {
+        Self { value: Box::new(value), type_name: std::any::type_name::<R>() }
+    }
+}
+
+/// Per component state which survives between frames. Positional hooks must be called in the same order every
+/// frame, use keyed hooks for anything created conditionally or in a loop.
 pub struct Context {
-    hooks: Vec<Box<dyn Any>>,
-    idx: Cell<usize>
+    hooks: Vec<Hook>,
+    keyed: HashMap<String, (Hook, u64)>,
+    cursor: usize,
+    frame: u64,
 }
 
 impl Context {
     fn new() -> Self {
         Self {
             hooks: Vec::new(),
-            idx: Cell::new(0)
+            keyed: HashMap::new(),
+            cursor: 0,
+            frame: 0,
         }
     }
 
-    pub fn reset(&self) {
-        self.idx.set(0);
+    fn begin(&mut self, frame: u64) {
+        debug_assert!(
+            self.frame == frame || self.cursor == self.hooks.len(),
+            "Only {} of {} hooks were used last frame, hooks must be called in the same order every frame",
+            self.cursor,
+            self.hooks.len()
+        );
+
+        self.cursor = 0;
+        self.frame = frame;
     }
 
     pub fn use_hook<F: FnOnce() -> R, R: 'static>(&mut self, func: F) -> &mut R {
-        let length = self.hooks.len();
+        let index = self.cursor;
+        self.cursor += 1;
 
-        if self.idx.get() <= length {
-            self.hooks.push(Box::new(func()));
+        if index == self.hooks.len() {
+            self.hooks.push(Hook::new(func()));
+        } else if !self.hooks[index].value.is::<R>() {
+            debug_assert!(
+                false,
+                "Hook {index} changed from {} to {}, hooks must be called in the same order every frame",
+                self.hooks[index].type_name,
+                std::any::type_name::<R>()
+            );
+
+            self.hooks[index] = Hook::new(func());
         };
 
-        let value = &mut self.hooks[self.idx.get()];
-        self.idx.set(length);
+        self.hooks[index].value.downcast_mut::<R>().unwrap()
+    }
+
+    /// A hook identified by `key` rather than call order, unused keys are dropped with the rest of the context's
+    /// stale state.
+    pub fn use_keyed_hook<F: FnOnce() -> R, R: 'static>(&mut self, key: impl Into<String>, func: F) -> &mut R {
+        let frame = self.frame;
+        let (hook, used) = self.keyed.entry(key.into()).or_insert_with(|| (Hook::new(func()), frame));
+
+        *used = frame;
 
-        return value.downcast_mut::<R>().unwrap()
+        if !hook.value.is::<R>() {
+            *hook = Hook::new(func());
+        };
+
+        hook.value.downcast_mut::<R>().unwrap()
     }
 
     pub fn use_state<F: FnOnce() -> R, R: Clone + 'static>(&mut self, func: F) -> &mut ContextState<R> {
         self.use_hook(|| ContextState::new(func()))
     }
+
+    /// Runs `func` on the first frame and again whenever `deps` changes.
+    pub fn use_effect<D: PartialEq + 'static>(&mut self, deps: D, func: impl FnOnce(&D)) {
+        let hook = self.use_hook(|| None::<D>);
+
+        if hook.as_ref() != Some(&deps) {
+            func(&deps);
+            *hook = Some(deps);
+        }
+    }
+
+    /// The value of `func`, only recomputed when `deps` changes.
+    pub fn use_memo<D: PartialEq + 'static, R: 'static>(&mut self, deps: D, func: impl FnOnce(&D) -> R) -> &R {
+        let hook = self.use_hook(|| None::<(D, R)>);
+
+        if hook.as_ref().map_or(true, |(old, _)| *old != deps) {
+            let value = func(&deps);
+            *hook = Some((deps, value));
+        };
+
+        &hook.as_ref().unwrap().1
+    }
+
+    /// Spawns the future returned by `func` on the runtime and polls for its result on later frames. The task is
+    /// restarted when `deps` changes and aborted when the context is dropped.
+    pub fn use_future<D, T, E, F, Fut>(&mut self, deps: D, func: F) -> &FutureState<T, E>
+    where
+        D: PartialEq + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+        F: FnOnce(&D) -> Fut,
+        Fut: Future<Output = Result<T, E>> + Send + 'static
+    {
+        let hook = self.use_hook(|| None::<FutureHook<D, T, E>>);
+
+        if hook.as_ref().map_or(true, |hook| hook.deps != deps) {
+            let (sender, receiver) = channel();
+            let future = func(&deps);
+
+            let task = tokio::spawn(async move {
+                sender.send(future.await).ok();
+            });
+
+            *hook = Some(FutureHook { deps, state: FutureState::Loading, receiver, _task: TaskHandle(task) });
+        };
+
+        let hook = hook.as_mut().unwrap();
+
+        if let Ok(result) = hook.receiver.try_recv() {
+            hook.state = match result {
+                Ok(value) => FutureState::Ready(value),
+                Err(e) => FutureState::Error(e)
+            };
+        };
+
+        &hook.state
+    }
+}
+
+#[derive(Clone)]
+pub enum FutureState<T, E> {
+    Loading,
+    Ready(T),
+    Error(E),
+}
+
+impl<T, E> FutureState<T, E> {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, FutureState::Ready(_))
+    }
+
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            FutureState::Ready(value) => Some(value),
+            _ => None
+        }
+    }
+}
+
+/// Aborts the task when dropped, so hooks don't leave work running for components which are gone.
+pub struct TaskHandle(pub JoinHandle<()>);
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A one-off background task for state which lives outside a [`Context`], polled by the UI every frame and aborted
+/// when dropped.
+pub struct Request<T> {
+    receiver: Receiver<T>,
+    _task: TaskHandle,
+}
+
+impl<T: Send + 'static> Request<T> {
+    pub fn spawn(future: impl Future<Output = T> + Send + 'static) -> Self {
+        let (sender, receiver) = channel();
+
+        let task = tokio::spawn(async move {
+            sender.send(future.await).ok();
+        });
+
+        Self { receiver, _task: TaskHandle(task) }
+    }
+
+    pub fn poll(&self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+struct FutureHook<D, T, E> {
+    deps: D,
+    state: FutureState<T, E>,
+    receiver: Receiver<Result<T, E>>,
+    _task: TaskHandle,
 }
 
 #[derive(Clone)]
@@ -62,53 +230,300 @@ impl<T: Clone> ContextState<T> {
     }
 }
 
-enum ConnectionState {
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// One line per message with the author repeated on every group.
+    Compact,
+    /// Large avatars with the author and time above each group.
+    Cozy,
+}
+
+pub struct Settings {
+    pub animate_on_hover_only: bool,
+    pub display_mode: DisplayMode,
+    pub clock_format: ClockFormat,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            animate_on_hover_only: false,
+            display_mode: DisplayMode::Cozy,
+            clock_format: ClockFormat::TwentyFourHour,
+        }
+    }
+}
+
+/// How long to wait after the last ack in a channel before telling the server.
+const ACK_DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[derive(Default, Clone)]
+pub struct ReadState {
+    pub last_id: Option<String>,
+    pub mentions: Vec<String>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct UnreadSummary {
+    pub unread: bool,
+    pub mentions: usize,
+}
+
+impl UnreadSummary {
+    fn merge(self, other: UnreadSummary) -> Self {
+        Self {
+            unread: self.unread || other.unread,
+            mentions: self.mentions + other.mentions,
+        }
+    }
+}
+
+pub enum ScrollTarget {
+    FirstUnread,
+    Present,
+}
+
+/// Scroll state of the message list for the currently open channel.
+#[derive(Default)]
+pub struct ChannelView {
+    pub channel_id: Option<String>,
+    pub first_unread: Option<String>,
+    pub at_bottom: bool,
+    pub scroll_to: Option<ScrollTarget>,
+}
+
+/// Unsaved changes to the role selected in the role editor.
+#[derive(Clone)]
+pub struct RoleDraft {
+    pub name: String,
+    pub colour: Option<[f32; 3]>,
+    pub hoist: bool,
+    pub allow: u64,
+    pub deny: u64,
+}
+
+pub struct RoleEditor {
+    pub server_id: String,
+    pub selected: Option<String>,
+    pub draft: Option<RoleDraft>,
+    /// Role ids from highest to lowest rank, reordered by dragging.
+    pub order: Vec<String>,
+    /// `order` as the server last had it, saving is only offered once they differ.
+    pub saved_order: Vec<String>,
+}
+
+impl RoleEditor {
+    pub fn new(server_id: String) -> Self {
+        Self {
+            server_id,
+            selected: None,
+            draft: None,
+            order: Vec::new(),
+            saved_order: Vec::new(),
+        }
+    }
+}
+
+pub enum MemberRow {
+    Header { name: String, count: usize },
+    Member(String),
+}
+
+/// The grouped and sorted member list, only rebuilt when the server, search or member data changes.
+#[derive(Default)]
+pub struct MemberList {
+    pub search: String,
+    /// Server, search and [`GlobalState::members_version`] the rows were built from.
+    pub built_from: Option<(String, String, u64)>,
+    pub rows: Vec<MemberRow>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ModerationKind {
+    Ban,
+    Kick,
+    Timeout,
+}
+
+impl ModerationKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            ModerationKind::Ban => "Ban Member",
+            ModerationKind::Kick => "Kick Member",
+            ModerationKind::Timeout => "Timeout Member",
+        }
+    }
+
+    pub fn done_message(self) -> &'static str {
+        match self {
+            ModerationKind::Ban => "Member banned",
+            ModerationKind::Kick => "Member kicked",
+            ModerationKind::Timeout => "Member timeout updated",
+        }
+    }
+}
+
+/// A ban, kick or timeout waiting for confirmation, and the result once it's been sent.
+pub struct ModerationDialog {
+    pub kind: ModerationKind,
+    pub server_id: String,
+    pub user_id: String,
+    pub reason: String,
+    pub delete_messages: bool,
+    /// Selected timeout preset in seconds, unless a custom duration is used.
+    pub duration: i64,
+    pub custom: bool,
+    pub custom_amount: i32,
+    pub custom_unit: usize,
+    pub opened: bool,
+    /// Actions still running, the outcome is set once they've all finished or one fails.
+    pub pending: Vec<ActionId>,
+    pub outcome: Option<Result<(), String>>,
+}
+
+impl ModerationDialog {
+    pub fn new(kind: ModerationKind, server_id: String, user_id: String) -> Self {
+        Self {
+            kind,
+            server_id,
+            user_id,
+            reason: String::new(),
+            delete_messages: false,
+            duration: 60 * 60,
+            custom: false,
+            custom_amount: 1,
+            custom_unit: 0,
+            opened: false,
+            pending: Vec::new(),
+            outcome: None,
+        }
+    }
+}
+
+/// A message being edited in place.
+pub struct MessageEdit {
+    pub channel_id: String,
+    pub message_id: String,
+    pub content: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
     Disconnected,
-    Connected
+    Reconnecting { attempt: u32 },
 }
 
 pub struct GlobalState {
     pub config: RevoltConfig,
+    pub user_id: Option<String>,
 
     pub servers: HashMap<String, Server>,
     pub users: HashMap<String, User>,
     pub members: HashMap<String, HashMap<String, Member>>,
     pub channels: HashMap<String, Channel>,
     pub messages: HashMap<String, Vec<Message>>,
+    /// Channels with a page of older messages being fetched.
+    pub history_loading: HashMap<String, ActionId>,
+    /// Channels with their latest messages being fetched, which happens when they're first opened.
+    pub latest_loading: HashMap<String, ActionId>,
+    /// Channels whose latest messages have been fetched, anything newer arrives over the gateway.
+    pub history_fresh: HashSet<String>,
+    /// Why fetching a channel's latest messages failed, cleared when it's tried again.
+    pub history_errors: HashMap<String, String>,
+    /// Channels where we've fetched back to the very first message.
+    pub history_start: HashSet<String>,
+    pub unreads: HashMap<String, ReadState>,
+    pending_acks: HashMap<String, (String, Instant)>,
+    /// Bumped whenever members, users or roles change so derived views know to rebuild.
+    pub members_version: u64,
+    /// Servers whose full member list has been fetched, `Ready` only includes some members.
+    pub full_member_lists: HashSet<String>,
+    pub profiles: HashMap<String, UserProfile>,
 
     pub current_message: String,
     pub current_message_hover: Option<String>,
     pub selected_server: Option<String>,
     pub selected_channel: Option<String>,
     pub selected_member: Option<String>,
+    pub viewed_attachment: Option<File>,
+    pub editing: Option<MessageEdit>,
+    pub channel_view: ChannelView,
+    pub role_editor: Option<RoleEditor>,
+    pub member_list: MemberList,
+    pub moderation: Option<ModerationDialog>,
+    pub bot_overview_open: bool,
 
     pub connection_state: ConnectionState,
+    /// Round trip time of the last gateway ping.
+    pub latency_ms: Option<i64>,
+    /// Set when the session stops being valid, the account is signed out on the next frame.
+    pub signed_out: Option<String>,
+
+    pub images: ImageCache,
+    pub fetcher: Fetcher,
+    pub notifications: Notifications,
+    pub settings: Settings,
+    pub clock: Clock,
 
     pub contexts: HashMap<String, Context>,
-    pub http: HttpClient
+    frame: u64,
+    http: HttpClient,
+    pub actions: Actions
 }
 
 impl GlobalState {
-    pub fn new(base_url: String, config: RevoltConfig, token: String) -> Self {
+    pub fn new(config: RevoltConfig, http: HttpClient, actions: Actions) -> Self {
+        let notifications = Notifications::new();
+
         Self {
             config,
+            user_id: None,
 
             servers: HashMap::new(),
             users: HashMap::new(),
             members: HashMap::new(),
             channels: HashMap::new(),
             messages: HashMap::new(),
+            history_loading: HashMap::new(),
+            latest_loading: HashMap::new(),
+            history_fresh: HashSet::new(),
+            history_errors: HashMap::new(),
+            history_start: HashSet::new(),
+            unreads: HashMap::new(),
+            pending_acks: HashMap::new(),
+            members_version: 0,
+            full_member_lists: HashSet::new(),
+            profiles: HashMap::new(),
 
             current_message: String::new(),
             current_message_hover: None,
             selected_server: None,
             selected_channel: None,
             selected_member: None,
+            viewed_attachment: None,
+            editing: None,
+            channel_view: ChannelView::default(),
+            role_editor: None,
+            member_list: MemberList::default(),
+            moderation: None,
+            bot_overview_open: false,
+
+            connection_state: ConnectionState::Connecting,
+            latency_ms: None,
+            signed_out: None,
 
-            connection_state: ConnectionState::Disconnected,
+            images: ImageCache::new(256, notifications.notifier()),
+            fetcher: Fetcher::new(),
+            notifications,
+            settings: Settings::default(),
+            clock: Clock::local(),
 
             contexts: HashMap::new(),
-            http: HttpClient::new(base_url, token)
+            frame: 0,
+            http,
+            actions
         }
     }
 
@@ -117,35 +532,351 @@ impl GlobalState {
             .entry(name.into())
             .or_insert_with(Context::new);
 
-        context.reset();
+        context.begin(self.frame);
 
         context
     }
+
+    /// Drops contexts and keyed hooks which haven't been used recently, called once at the end of every frame.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+
+        self.contexts.retain(|_, context| frame - context.frame < CONTEXT_TTL_FRAMES);
+
+        for context in self.contexts.values_mut() {
+            context.keyed.retain(|_, (_, used)| frame - *used < CONTEXT_TTL_FRAMES);
+        }
+
+        self.frame += 1;
+    }
+
+    pub fn set_unreads(&mut self, unreads: Vec<ChannelUnread>) {
+        for unread in unreads {
+            self.unreads.insert(unread.id.channel, ReadState {
+                last_id: unread.last_id,
+                mentions: unread.mentions,
+            });
+        }
+    }
+
+    /// Base URL of the instance's API.
+    pub fn api_base(&self) -> &str {
+        &self.http.base
+    }
+
+    /// Bots have no friends, unreads or synced settings, those parts of the UI are hidden for them.
+    pub fn is_bot(&self) -> bool {
+        self.http.kind == TokenKind::Bot
+    }
+
+    pub fn unread_summary(&self, channel_id: &str) -> UnreadSummary {
+        let last_message_id = match self.channels.get(channel_id) {
+            Some(Channel::DirectMessage { last_message_id, .. } | Channel::Group { last_message_id, .. } | Channel::TextChannel { last_message_id, .. }) => last_message_id.as_deref(),
+            _ => None
+        };
+
+        let read_state = self.unreads.get(channel_id);
+
+        UnreadSummary {
+            unread: match (last_message_id, read_state.and_then(|r| r.last_id.as_deref())) {
+                (Some(last_message), Some(last_read)) => last_message > last_read,
+                // Bots don't have read state on the server, so only count what's arrived since they last looked.
+                (Some(_), None) => !self.is_bot(),
+                (None, _) => false,
+            },
+            mentions: read_state.map(|r| r.mentions.len()).unwrap_or_default(),
+        }
+    }
+
+    /// Rolls up the unread state of several channels, used for servers and categories.
+    pub fn channels_unread_summary<'a>(&self, channel_ids: impl IntoIterator<Item = &'a String>) -> UnreadSummary {
+        channel_ids.into_iter()
+            .map(|id| self.unread_summary(id))
+            .fold(UnreadSummary::default(), UnreadSummary::merge)
+    }
+
+    /// Marks the channel as read up to `message_id`, the request is only sent once acks settle.
+    pub fn ack(&mut self, channel_id: &str, message_id: &str) {
+        let read_state = self.unreads.entry(channel_id.to_string()).or_default();
+
+        read_state.last_id = Some(message_id.to_string());
+        read_state.mentions.clear();
+
+        // Queueing the same ack again keeps its timestamp, otherwise it would never settle.
+        if !self.is_bot() && self.pending_acks.get(channel_id).map_or(true, |(pending, _)| pending != message_id) {
+            self.pending_acks.insert(channel_id.to_string(), (message_id.to_string(), Instant::now()));
+        };
+    }
+
+    pub fn flush_acks(&mut self) {
+        let now = Instant::now();
+
+        let ready = self.pending_acks.iter()
+            .filter(|(_, (_, at))| now - *at >= ACK_DEBOUNCE)
+            .map(|(channel, _)| channel.clone())
+            .collect::<Vec<_>>();
+
+        for channel in ready {
+            if let Some((message, _)) = self.pending_acks.remove(&channel) {
+                self.actions.dispatch(Action::Ack { channel, message });
+            }
+        }
+    }
+
+    /// Fetches a user who isn't cached yet, callers should draw a placeholder until it arrives.
+    pub fn request_user(&mut self, user_id: &str) {
+        if !self.users.contains_key(user_id) {
+            self.fetcher.request(&self.actions, FetchKey::User(user_id.to_string()));
+        }
+    }
+
+    pub fn request_member(&mut self, server_id: &str, user_id: &str) {
+        let cached = self.members.get(server_id).map_or(true, |members| members.contains_key(user_id));
+
+        if !cached && !self.fetcher.is_loading(&FetchKey::AllMembers(server_id.to_string())) {
+            self.fetcher.request(&self.actions, FetchKey::Member { server: server_id.to_string(), user: user_id.to_string() });
+        }
+    }
+
+    /// Fetches a user's profile, any cached one is shown until the new one arrives.
+    pub fn request_profile(&mut self, user_id: &str) {
+        self.fetcher.request(&self.actions, FetchKey::Profile(user_id.to_string()));
+    }
+
+    pub fn load_all_members(&mut self, server_id: &str) {
+        self.fetcher.request(&self.actions, FetchKey::AllMembers(server_id.to_string()));
+    }
+
+    /// Applies the result of an action dispatched through [`Actions`].
+    pub fn action_finished(&mut self, id: ActionId, result: Result<Output, ActionError>) {
+        self.fetcher.finished(id, &result);
+
+        if let Some(dialog) = self.moderation.as_mut().filter(|dialog| dialog.pending.contains(&id)) {
+            dialog.pending.retain(|pending| *pending != id);
+
+            match &result {
+                Err(e) => dialog.outcome = Some(Err(e.message.clone())),
+                Ok(_) if dialog.pending.is_empty() && dialog.outcome.is_none() => {
+                    dialog.outcome = Some(Ok(()));
+                    self.notifications.push(Notification::success(dialog.kind.done_message()));
+                },
+                Ok(_) => {}
+            }
+        };
+
+        match &result {
+            Err(e) if e.cancelled => {},
+            Err(e) if e.background => self.notifications.push(Notification::problem(format!("Couldn't {}", e.action), &e.message)),
+            Err(e) => self.notifications.push(Notification::error(format!("Couldn't {}", e.action), &e.message)),
+            Ok(_) => {}
+        };
+
+        self.history_loading.retain(|_, pending| *pending != id);
+
+        if let Some(channel_id) = self.latest_loading.iter().find(|(_, pending)| **pending == id).map(|(channel, _)| channel.clone()) {
+            self.latest_loading.remove(&channel_id);
+
+            match &result {
+                Ok(_) => { self.history_fresh.insert(channel_id); },
+                Err(e) if !e.cancelled => { self.history_errors.insert(channel_id, e.message.clone()); },
+                Err(_) => {}
+            }
+        };
+
+        match result {
+            Ok(Output::History(channel_id, history)) => self.apply_history(&channel_id, &history),
+            Ok(Output::Profile(user_id, profile)) => { self.profiles.insert(user_id, profile); },
+            Ok(Output::User(user)) => {
+                self.users.insert(user.id.clone(), user);
+                self.members_version += 1;
+            },
+            Ok(Output::Member(member)) => {
+                if let Some(members) = self.members.get_mut(&member.id.server) {
+                    members.insert(member.id.user.clone(), member);
+                };
+
+                self.members_version += 1;
+            },
+            Ok(Output::Members(server_id, response)) => {
+                for user in response.users {
+                    self.users.insert(user.id.clone(), user);
+                };
+
+                if let Some(members) = self.members.get_mut(&server_id) {
+                    members.extend(response.members.into_iter().map(|member| (member.id.user.clone(), member)));
+                };
+
+                self.full_member_lists.insert(server_id);
+                self.members_version += 1;
+            },
+            Ok(Output::Unreads(unreads)) => self.set_unreads(unreads),
+            Ok(Output::None) | Err(_) => {}
+        }
+    }
+
+    /// Fetches the latest page of messages unless it's already been fetched or is on its way.
+    pub fn load_latest_messages(&mut self, channel_id: &str) {
+        if self.latest_loading.contains_key(channel_id) || self.history_fresh.contains(channel_id) {
+            return
+        };
+
+        self.history_errors.remove(channel_id);
+
+        let id = self.actions.dispatch(Action::FetchHistory { channel: channel_id.to_string(), before: None });
+
+        self.latest_loading.insert(channel_id.to_string(), id);
+    }
+
+    /// Fetches the page of messages before the oldest one we have.
+    pub fn load_older_messages(&mut self, channel_id: &str) {
+        if self.history_loading.contains_key(channel_id) || self.latest_loading.contains_key(channel_id) || self.history_start.contains(channel_id) {
+            return
+        };
+
+        let before = self.messages.get(channel_id)
+            .and_then(|messages| messages.first())
+            .map(|message| message.id.clone());
+
+        let id = self.actions.dispatch(Action::FetchHistory { channel: channel_id.to_string(), before });
+
+        self.history_loading.insert(channel_id.to_string(), id);
+    }
+
+    /// Merges a page of fetched messages into the channel, keeping messages we already have. A short page means
+    /// there's nothing older left to fetch.
+    pub fn apply_history(&mut self, channel_id: &str, history: &BulkMessageResponse) {
+        let messages = match history {
+            BulkMessageResponse::JustMessages(messages) => messages,
+            BulkMessageResponse::MessagesAndUsers { messages, users, members } => {
+                for user in users {
+                    self.users.insert(user.id.clone(), user.clone());
+                };
+
+                for member in members.iter().flatten() {
+                    if let Some(members) = self.members.get_mut(&member.id.server) {
+                        members.insert(member.id.user.clone(), member.clone());
+                    }
+                };
+
+                self.members_version += 1;
+
+                messages
+            }
+        };
+
+        if messages.len() < HISTORY_PAGE_SIZE {
+            self.history_start.insert(channel_id.to_string());
+        };
+
+        let channel_messages = self.messages.entry(channel_id.to_string()).or_default();
+
+        channel_messages.extend(messages.iter().cloned());
+        channel_messages.sort_by(|a, b| a.id.cmp(&b.id));
+        channel_messages.dedup_by(|a, b| a.id == b.id);
+    }
+
+    /// The other participant of a direct message channel.
+    pub fn dm_recipient(&self, channel: &Channel) -> Option<&User> {
+        match channel {
+            Channel::DirectMessage { recipients, .. } => recipients.iter()
+                .find(|id| Some(id.as_str()) != self.user_id.as_deref())
+                .and_then(|id| self.users.get(id)),
+            _ => None
+        }
+    }
+
+    pub fn channel_name(&self, channel: &Channel) -> String {
+        match channel {
+            Channel::SavedMessages { .. } => "Saved Messages".to_string(),
+            Channel::DirectMessage { .. } => self.dm_recipient(channel)
+                .map(|user| user.display_name.clone().unwrap_or_else(|| user.username.clone()))
+                .unwrap_or_else(|| "Unknown User".to_string()),
+            Channel::Group { name, .. } | Channel::TextChannel { name, .. } | Channel::VoiceChannel { name, .. } => name.clone(),
+        }
+    }
+
+    /// Saved Messages first, then open direct messages and groups with the most recent activity first.
+    pub fn direct_message_channels(&self) -> Vec<String> {
+        let mut channels = self.channels.values()
+            .filter(|channel| match channel {
+                Channel::SavedMessages { .. } | Channel::Group { .. } => true,
+                Channel::DirectMessage { active, .. } => *active,
+                _ => false
+            })
+            .collect::<Vec<_>>();
+
+        channels.sort_by(|a, b| {
+            let key = |channel: &Channel| match channel {
+                Channel::SavedMessages { .. } => (true, None),
+                Channel::DirectMessage { last_message_id, .. } | Channel::Group { last_message_id, .. } => (false, last_message_id.clone()),
+                _ => (false, None)
+            };
+
+            key(b).cmp(&key(a))
+        });
+
+        channels.into_iter().map(|channel| channel.id().to_string()).collect()
+    }
+}
+
+fn find_message<'a>(state: &'a mut GlobalState, channel_id: &str, message_id: &str) -> Option<&'a mut Message> {
+    state.messages.get_mut(channel_id)?
+        .iter_mut()
+        .find(|message| message.id == message_id)
+}
+
+pub fn update_state(event: AppEvent, state: &mut GlobalState) {
+    match event {
+        AppEvent::Gateway(event) => apply_event(event, state),
+        AppEvent::ActionFinished { id, result } => state.action_finished(id, result),
+        AppEvent::Notify(notification) => state.notifications.push(notification),
+        AppEvent::Connection(connection_state) => {
+            if connection_state != ConnectionState::Connected {
+                state.latency_ms = None;
+            };
+
+            state.connection_state = connection_state;
+        },
+        AppEvent::SignedOut(reason) => {
+            state.connection_state = ConnectionState::Disconnected;
+            state.signed_out = Some(reason);
+        },
+    }
 }
 
-pub fn update_state(event: EventV1, state: &mut GlobalState) {
+fn apply_event(event: EventV1, state: &mut GlobalState) {
     match event {
         EventV1::Bulk { v } => {
             for e in v {
-                update_state(e, state)
+                apply_event(e, state)
             }
         },
         EventV1::Authenticated => {
             state.connection_state = ConnectionState::Connected
         },
         EventV1::Logout => {},
+        EventV1::Pong { data } => {
+            if let Ping::Number(sent_ms) = data {
+                state.latency_ms = Some(Clock::now_ms() - sent_ms as i64);
+            }
+        },
         EventV1::Ready { users, servers, channels, members, emojis: _ } => {
+            state.user_id = users.iter()
+                .find(|user| matches!(user.relationship, RelationshipStatus::User))
+                .map(|user| user.id.clone());
+
             for user in users {
                 state.users.insert(user.id.clone(), user);
             };
 
             for server in servers {
-                state.members.insert(server.id.clone(), HashMap::new());
+                // Keep what we've already loaded when this is a reconnection.
+                state.members.entry(server.id.clone()).or_default();
                 state.servers.insert(server.id.clone(), server);
             };
 
             for channel in channels {
-                state.messages.insert(channel.id().to_string(), Vec::new());
+                state.messages.entry(channel.id().to_string()).or_default();
                 state.channels.insert(channel.id().to_string(), channel);
             };
 
@@ -153,19 +884,210 @@ pub fn update_state(event: EventV1, state: &mut GlobalState) {
                 state.members.get_mut(&member.id.server)
                     .map(|members| members.insert(member.id.user.clone(), member));
             };
+
+            // Anything sent while we were disconnected is missing, channels fetch their latest messages again when
+            // they're next shown.
+            state.history_fresh.clear();
+            state.history_errors.clear();
+
+            state.members_version += 1;
         },
         EventV1::Message(mut message) => {
             if let Some(user) = message.user.take() {
                 state.users.insert(user.id.clone(), user);
+                state.members_version += 1;
             };
 
             if let Some(member) = message.member.take() {
                 state.members.get_mut(&member.id.server)
                     .map(|members| members.insert(member.id.user.clone(), member));
+                state.members_version += 1;
+            };
+
+            match state.channels.get_mut(&message.channel) {
+                Some(Channel::DirectMessage { last_message_id, active, .. }) => {
+                    *last_message_id = Some(message.id.clone());
+                    *active = true;
+                },
+                Some(Channel::Group { last_message_id, .. } | Channel::TextChannel { last_message_id, .. }) => {
+                    *last_message_id = Some(message.id.clone());
+                },
+                _ => {}
+            };
+
+            if state.user_id.as_ref().is_some_and(|id| message.mentions.as_ref().is_some_and(|m| m.contains(id))) {
+                state.unreads.entry(message.channel.clone())
+                    .or_default()
+                    .mentions
+                    .push(message.id.clone());
             };
 
             state.messages.get_mut(&message.channel)
                 .map(|messages| messages.push(message));
+        },
+        EventV1::ServerMemberUpdate { id, data, clear } => {
+            if let Some(member) = state.members.get_mut(&id.server).and_then(|members| members.get_mut(&id.user)) {
+                for field in clear {
+                    match field {
+                        FieldsMember::Nickname => member.nickname = None,
+                        FieldsMember::Avatar => member.avatar = None,
+                        FieldsMember::Roles => member.roles.clear(),
+                        FieldsMember::Timeout => member.timeout = None,
+                    }
+                }
+
+                if let Some(nickname) = data.nickname { member.nickname = Some(nickname) };
+                if let Some(avatar) = data.avatar { member.avatar = Some(avatar) };
+                if let Some(roles) = data.roles { member.roles = roles };
+                if let Some(timeout) = data.timeout { member.timeout = Some(timeout) };
+            }
+
+            state.members_version += 1;
+        },
+        EventV1::ServerMemberJoin { id, user, .. } => {
+            if let Some(members) = state.members.get_mut(&id) {
+                members.insert(user.clone(), Member {
+                    id: MemberCompositeKey { server: id, user },
+                    joined_at: Timestamp::now_utc(),
+                    nickname: None,
+                    avatar: None,
+                    roles: Vec::new(),
+                    timeout: None,
+                });
+            };
+
+            state.members_version += 1;
+        },
+        EventV1::ServerMemberLeave { id, user, .. } => {
+            if let Some(members) = state.members.get_mut(&id) {
+                members.remove(&user);
+            };
+
+            if state.user_id.as_deref() == Some(user.as_str()) {
+                state.members.remove(&id);
+                state.servers.remove(&id);
+
+                if state.selected_server.as_deref() == Some(id.as_str()) {
+                    state.selected_server = None;
+                    state.selected_channel = None;
+                }
+            };
+
+            state.members_version += 1;
+        },
+        EventV1::ServerRoleUpdate { id, role_id, data, clear } => {
+            if let Some(server) = state.servers.get_mut(&id) {
+                let role = server.roles.entry(role_id).or_insert_with(|| Role {
+                    name: String::new(),
+                    permissions: OverrideField { a: 0, d: 0 },
+                    colour: None,
+                    hoist: false,
+                    rank: 0,
+                });
+
+                for field in clear {
+                    match field {
+                        FieldsRole::Colour => role.colour = None,
+                    }
+                }
+
+                if let Some(name) = data.name { role.name = name };
+                if let Some(permissions) = data.permissions { role.permissions = permissions };
+                if let Some(colour) = data.colour { role.colour = Some(colour) };
+                if let Some(hoist) = data.hoist { role.hoist = hoist };
+                if let Some(rank) = data.rank { role.rank = rank };
+            }
+
+            state.members_version += 1;
+        },
+        EventV1::ServerRoleDelete { id, role_id } => {
+            if let Some(server) = state.servers.get_mut(&id) {
+                server.roles.remove(&role_id);
+            };
+
+            if let Some(members) = state.members.get_mut(&id) {
+                for member in members.values_mut() {
+                    member.roles.retain(|role| role != &role_id);
+                }
+            };
+
+            state.members_version += 1;
+        },
+        EventV1::UserUpdate { id, data, clear, .. } => {
+            if let Some(user) = state.users.get_mut(&id) {
+                for field in clear {
+                    match field {
+                        FieldsUser::Avatar => user.avatar = None,
+                        FieldsUser::StatusText => if let Some(status) = &mut user.status { status.text = None },
+                        FieldsUser::StatusPresence => if let Some(status) = &mut user.status { status.presence = None },
+                        FieldsUser::DisplayName => user.display_name = None,
+                        _ => {}
+                    }
+                }
+
+                if let Some(username) = data.username { user.username = username };
+                if let Some(discriminator) = data.discriminator { user.discriminator = discriminator };
+                if let Some(display_name) = data.display_name { user.display_name = Some(display_name) };
+                if let Some(avatar) = data.avatar { user.avatar = Some(avatar) };
+                if let Some(status) = data.status { user.status = Some(status) };
+                if let Some(online) = data.online { user.online = online };
+            };
+
+            state.members_version += 1;
+        },
+        EventV1::ChannelAck { id, user, message_id } => {
+            if state.user_id.as_deref() == Some(user.as_str()) {
+                let read_state = state.unreads.entry(id).or_default();
+
+                read_state.mentions.retain(|mention| mention > &message_id);
+                read_state.last_id = Some(message_id);
+            }
+        },
+        EventV1::MessageUpdate { id, channel, data, .. } => {
+            if let Some(message) = find_message(state, &channel, &id) {
+                if let Some(content) = data.content { message.content = Some(content) };
+                if let Some(edited) = data.edited { message.edited = Some(edited) };
+                if let Some(embeds) = data.embeds { message.embeds = Some(embeds) };
+            }
+        },
+        EventV1::MessageReact { id, channel_id, user_id, emoji_id } => {
+            if let Some(message) = find_message(state, &channel_id, &id) {
+                message.reactions.entry(emoji_id).or_default().insert(user_id);
+            }
+        },
+        EventV1::MessageUnreact { id, channel_id, user_id, emoji_id } => {
+            if let Some(message) = find_message(state, &channel_id, &id) {
+                if let Some(users) = message.reactions.get_mut(&emoji_id) {
+                    users.shift_remove(&user_id);
+
+                    if users.is_empty() {
+                        message.reactions.shift_remove(&emoji_id);
+                    }
+                }
+            }
+        },
+        EventV1::MessageRemoveReaction { id, channel_id, emoji_id } => {
+            if let Some(message) = find_message(state, &channel_id, &id) {
+                message.reactions.shift_remove(&emoji_id);
+            }
+        },
+        EventV1::MessageDelete { id, channel } => {
+            if let Some(messages) = state.messages.get_mut(&channel) {
+                messages.retain(|message| message.id != id);
+            }
+        },
+        EventV1::BulkMessageDelete { channel, ids } => {
+            if let Some(messages) = state.messages.get_mut(&channel) {
+                messages.retain(|message| !ids.contains(&message.id));
+            }
+        },
+        EventV1::MessageAppend { id, channel, append } => {
+            let message = state.messages.get_mut(&channel)
+                .and_then(|messages| messages.iter_mut().find(|m| m.id == id));
+
+            if let (Some(message), Some(embeds)) = (message, append.embeds) {
+                message.embeds.get_or_insert_with(Vec::new).extend(embeds);
+            }
         }
         event => {
             println!("Unhandled Event {:?}", event);
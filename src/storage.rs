@@ -0,0 +1,63 @@
+use std::{fs, io::{self, Write}, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Where our files live, `~/.config/imrevolt` on Linux.
+pub fn dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("imrevolt"))
+}
+
+/// Reads a file we saved earlier, `None` if it hasn't been saved yet.
+pub fn load<T: DeserializeOwned>(name: &str) -> Result<Option<T>, String> {
+    let Some(path) = dir().map(|dir| dir.join(name)) else {
+        return Ok(None)
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Couldn't read {}: {e}", path.display()))
+    };
+
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| format!("Couldn't parse {}: {e}", path.display()))
+}
+
+/// Writes `value` so only the current user can read it, some of these files hold session tokens.
+pub fn save_private<T: Serialize>(name: &str, value: &T) -> io::Result<()> {
+    let dir = dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+    fs::create_dir_all(&dir)?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(dir.join(name))?;
+
+    // The mode only applies to new files, tighten up anything left over from before.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(serde_json::to_string_pretty(value)?.as_bytes())
+}
+
+/// Removes a file if it's there.
+pub fn remove(name: &str) -> io::Result<()> {
+    let Some(path) = dir().map(|dir| dir.join(name)) else {
+        return Ok(())
+    };
+
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(())
+    }
+}
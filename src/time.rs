@@ -0,0 +1,141 @@
+use eos::{DateTime, Timestamp, UtcOffset};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClockFormat {
+    TwelveHour,
+    TwentyFourHour,
+}
+
+/// Converts timestamps into the system's local time zone, read from `TZ` or `/etc/localtime`.
+pub struct Clock {
+    zone: Option<eos_tz::Local>,
+}
+
+impl Clock {
+    pub fn local() -> Self {
+        let zone = eos_tz::Local::new()
+            .map_err(|e| eprintln!("Failed to load local time zone, falling back to UTC: {e}"))
+            .ok();
+
+        Self { zone }
+    }
+
+    pub fn now_ms() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default()
+    }
+
+    pub fn from_millis(&self, timestamp_ms: i64) -> DateTime<UtcOffset> {
+        let timestamp = Timestamp::from_milliseconds(timestamp_ms);
+
+        let offset = match &self.zone {
+            Some(zone) => *DateTime::from_timestamp(timestamp, zone.clone()).offset(),
+            None => UtcOffset::UTC
+        };
+
+        DateTime::from_timestamp(timestamp, offset)
+    }
+
+    /// When a message was created, taken from its ULID.
+    pub fn from_id(&self, id: &str) -> DateTime<UtcOffset> {
+        self.from_millis(id_timestamp_ms(id))
+    }
+
+    pub fn is_same_day(&self, a_ms: i64, b_ms: i64) -> bool {
+        self.from_millis(a_ms).date() == self.from_millis(b_ms).date()
+    }
+
+    pub fn time(&self, datetime: &DateTime<UtcOffset>, format: ClockFormat) -> String {
+        match format {
+            ClockFormat::TwelveHour => eos::format_dt!("%I:%M %p", datetime).to_string(),
+            ClockFormat::TwentyFourHour => eos::format_dt!("%H:%M", datetime).to_string(),
+        }
+    }
+
+    pub fn time_with_seconds(&self, datetime: &DateTime<UtcOffset>, format: ClockFormat) -> String {
+        match format {
+            ClockFormat::TwelveHour => eos::format_dt!("%I:%M:%S %p", datetime).to_string(),
+            ClockFormat::TwentyFourHour => eos::format_dt!("%H:%M:%S", datetime).to_string(),
+        }
+    }
+
+    pub fn short_date(&self, datetime: &DateTime<UtcOffset>) -> String {
+        eos::format_dt!("%d/%m/%Y", datetime).to_string()
+    }
+
+    pub fn long_date(&self, datetime: &DateTime<UtcOffset>) -> String {
+        eos::format_dt!("%d %B %Y", datetime).to_string()
+    }
+
+    /// The full date and time, used for tooltips.
+    pub fn full(&self, timestamp_ms: i64, format: ClockFormat) -> String {
+        let datetime = self.from_millis(timestamp_ms);
+
+        format!("{}, {} {}", eos::format_dt!("%A", datetime), self.long_date(&datetime), self.time_with_seconds(&datetime, format))
+    }
+
+    /// "Today", "Yesterday" or the date of the given timestamp.
+    pub fn day(&self, timestamp_ms: i64) -> String {
+        let now = Self::now_ms();
+
+        if self.is_same_day(timestamp_ms, now) {
+            "Today".to_string()
+        } else if self.is_same_day(timestamp_ms, now - DAY_MS) {
+            "Yesterday".to_string()
+        } else {
+            let datetime = self.from_millis(timestamp_ms);
+
+            format!("{}, {}", eos::format_dt!("%A", datetime), self.long_date(&datetime))
+        }
+    }
+
+    /// "Today at 14:03", "Yesterday at 09:12" or the short date and time.
+    pub fn relative(&self, timestamp_ms: i64, format: ClockFormat) -> String {
+        let now = Self::now_ms();
+        let datetime = self.from_millis(timestamp_ms);
+        let time = self.time(&datetime, format);
+
+        if self.is_same_day(timestamp_ms, now) {
+            format!("Today at {time}")
+        } else if self.is_same_day(timestamp_ms, now - DAY_MS) {
+            format!("Yesterday at {time}")
+        } else {
+            format!("{} {time}", self.short_date(&datetime))
+        }
+    }
+}
+
+/// A UTC timestamp in the ISO 8601 form the API expects, e.g. `2024-01-31T18:00:00Z`.
+pub fn iso8601(timestamp_ms: i64) -> String {
+    let datetime = DateTime::from_timestamp(Timestamp::from_milliseconds(timestamp_ms), UtcOffset::UTC);
+
+    eos::format_dt!("%Y-%m-%dT%H:%M:%SZ", datetime).to_string()
+}
+
+pub fn id_timestamp_ms(id: &str) -> i64 {
+    ulid::Ulid::from_string(id).map(|id| id.timestamp_ms() as i64).unwrap_or_default()
+}
+
+/// "in 5 minutes" or "3 hours ago", the largest whole unit of the difference from now.
+pub fn from_now(timestamp_ms: i64) -> String {
+    let difference = timestamp_ms - Clock::now_ms();
+    let seconds = difference.abs() / 1000;
+
+    let (amount, unit) = [(365 * 24 * 60 * 60, "year"), (30 * 24 * 60 * 60, "month"), (24 * 60 * 60, "day"), (60 * 60, "hour"), (60, "minute")]
+        .into_iter()
+        .find(|(size, _)| seconds >= *size)
+        .map(|(size, unit)| (seconds / size, unit))
+        .unwrap_or((seconds, "second"));
+
+    let plural = if amount == 1 { "" } else { "s" };
+
+    if difference >= 0 {
+        format!("in {amount} {unit}{plural}")
+    } else {
+        format!("{amount} {unit}{plural} ago")
+    }
+}
@@ -1,6 +1,6 @@
-use std::sync::mpsc::Sender;
+use std::time::Duration;
 use futures::{SinkExt, StreamExt};
-use revolt_database::events::client::{EventV1, Ping};
+use revolt_database::events::client::{EventV1, Ping, WebSocketError};
 use serde::Serialize;
 use tokio_tungstenite::connect_async;
 
@@ -14,24 +14,105 @@ pub enum ClientMessage {
     Ping { data: Ping, responded: Option<()> },
 }
 
-use crate::http::RevoltConfig;
+use crate::{actions::{AppEvent, EventSender}, http::RevoltConfig, notifications::Notification, state::ConnectionState, time::Clock};
 
-pub async fn run(event_sender: Sender<EventV1>, token: String, api_info: RevoltConfig) {
-    let (ws, _) = connect_async(&api_info.ws).await.unwrap();
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+fn text(message: &ClientMessage) -> tungstenite::Message {
+    tungstenite::Message::Text(serde_json::to_string(message).unwrap())
+}
+
+/// How a connection ended when it wasn't an error.
+enum Closed {
+    /// The server hung up or the UI is gone, worth reconnecting if it's the former.
+    Dropped,
+    /// The token was rejected or the session was logged out, reconnecting won't help.
+    SignedOut(&'static str),
+}
+
+/// Keeps a gateway connection open, reconnecting with a backoff whenever it drops. Returns once the UI is gone or the
+/// session is no longer valid.
+pub async fn run(event_sender: EventSender, token: String, api_info: RevoltConfig) {
+    let mut attempt = 0;
+
+    loop {
+        let connection_state = if attempt == 0 { ConnectionState::Connecting } else { ConnectionState::Reconnecting { attempt } };
+
+        if event_sender.send(AppEvent::Connection(connection_state)).is_err() {
+            return
+        };
+
+        let mut established = false;
+
+        match session(&event_sender, &token, &api_info, &mut established).await {
+            Ok(Closed::SignedOut(reason)) => {
+                event_sender.send(AppEvent::SignedOut(reason.to_string())).ok();
+                return
+            },
+            Ok(Closed::Dropped) => {},
+            Err(e) => {
+                event_sender.send(AppEvent::Notify(Notification::problem("Gateway connection lost", e.to_string()))).ok();
+            }
+        };
+
+        if event_sender.send(AppEvent::Connection(ConnectionState::Disconnected)).is_err() {
+            return
+        };
+
+        attempt = if established { 1 } else { attempt + 1 };
+
+        let delay = Duration::from_secs(1 << attempt.min(5)).min(MAX_RECONNECT_DELAY);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// A single connection, returns when it closes. `established` is set once the server accepts our token.
+async fn session(event_sender: &EventSender, token: &str, api_info: &RevoltConfig, established: &mut bool) -> Result<Closed, tungstenite::Error> {
+    let (ws, _) = connect_async(&api_info.ws).await?;
 
     let (mut ws_send, mut ws_receive) = ws.split();
 
-    let send = |e: ClientMessage| async move {
-        ws_send.send(tungstenite::Message::Text(serde_json::to_string(&e).unwrap())).await
-    };
+    ws_send.send(text(&ClientMessage::Authenticate { token: token.to_string() })).await?;
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
 
-    send(ClientMessage::Authenticate { token }).await.unwrap();
+    loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                let data = Ping::Number(Clock::now_ms() as usize);
 
-    tokio::spawn(async move {
-        while let Some(Ok(msg)) = ws_receive.next().await {
-            if let Ok(data) = msg.to_text() {
-                event_sender.send(serde_json::from_str(data).unwrap()).unwrap();
+                ws_send.send(text(&ClientMessage::Ping { data, responded: None })).await?;
+            },
+            msg = ws_receive.next() => {
+                let msg = match msg {
+                    Some(msg) => msg?,
+                    None => return Ok(Closed::Dropped)
+                };
+
+                if !msg.is_text() {
+                    continue
+                };
+
+                let data = msg.to_text()?;
+
+                let event = match serde_json::from_str(data) {
+                    Ok(EventV1::Authenticated) => {
+                        *established = true;
+                        AppEvent::Gateway(EventV1::Authenticated)
+                    },
+                    Ok(EventV1::Logout) => return Ok(Closed::SignedOut("The session was logged out")),
+                    Ok(EventV1::Error { data: WebSocketError::InvalidSession }) => return Ok(Closed::SignedOut("The session is no longer valid")),
+                    Ok(EventV1::Error { data: WebSocketError::OnboardingNotFinished }) => return Ok(Closed::SignedOut("The account hasn't finished setting up")),
+                    Ok(EventV1::Error { data }) => AppEvent::Notify(Notification::problem("The gateway reported an error", format!("{data:?}"))),
+                    Ok(event) => AppEvent::Gateway(event),
+                    Err(e) => AppEvent::Notify(Notification::problem("Couldn't decode a gateway event", e.to_string()))
+                };
+
+                if event_sender.send(event).is_err() {
+                    return Ok(Closed::Dropped)
+                };
             }
         }
-    });
-}
\ No newline at end of file
+    }
+}